@@ -1,5 +1,9 @@
 use crate::{
+    clock::Clock,
     digest::{DefaultDigester, Digester},
+    observer::Observer,
+    payload::{PayloadHeader, PayloadSink},
+    transport::Transport,
     urc_matcher::{DefaultUrcMatcher, UrcMatcher},
     Client, Config, IngressManager, Queues,
 };
@@ -18,41 +22,45 @@ type ClientParser<Tx, T, U, D, const BUF_LEN: usize, const URC_CAPACITY: usize>
 /// [`new`]: #method.new
 pub struct ClientBuilder<Tx, T, U, D, const BUF_LEN: usize, const URC_CAPACITY: usize>
 where
-    Tx: embedded_hal::serial::Write<u8>,
-    T: embedded_hal::timer::CountDown,
-    T::Time: From<u32>,
+    Tx: Transport,
+    T: Clock,
     U: UrcMatcher,
     D: Digester,
 {
     serial_tx: Tx,
-    timer: T,
+    clock: T,
     config: Config,
     custom_urc_matcher: U,
     custom_digester: D,
+    observer: Option<&'static dyn Observer>,
+    payload_sink: Option<(&'static [PayloadHeader], &'static dyn PayloadSink)>,
 }
 
 impl<Tx, T, const BUF_LEN: usize, const URC_CAPACITY: usize>
     ClientBuilder<Tx, T, DefaultUrcMatcher, DefaultDigester, BUF_LEN, URC_CAPACITY>
 where
-    Tx: embedded_hal::serial::Write<u8>,
-    T: embedded_hal::timer::CountDown,
-    T::Time: From<u32>,
+    Tx: Transport,
+    T: Clock,
 {
     /// Create a builder for new Atat client instance.
     ///
-    /// The `serial_tx` type must implement the `embedded_hal`
-    /// [`serial::Write<u8>`][serialwrite] trait while the timer must implement
-    /// the [`timer::CountDown`][timercountdown] trait.
+    /// The `serial_tx` type must implement the [`Transport`][transport]
+    /// trait, which is implemented for every `embedded_hal`
+    /// [`serial::Write<u8>`][serialwrite], while the clock must implement the
+    /// monotonic [`Clock`][clock] trait.
     ///
+    /// [transport]: transport/trait.Transport.html
     /// [serialwrite]: ../embedded_hal/serial/trait.Write.html
-    /// [timercountdown]: ../embedded_hal/timer/trait.CountDown.html
-    pub fn new(serial_tx: Tx, timer: T, config: Config) -> Self {
+    /// [clock]: clock/trait.Clock.html
+    pub fn new(serial_tx: Tx, clock: T, config: Config) -> Self {
         Self {
             serial_tx,
-            timer,
+            clock,
             config,
             custom_urc_matcher: DefaultUrcMatcher::default(),
             custom_digester: DefaultDigester::default(),
+            observer: None,
+            payload_sink: None,
         }
     }
 }
@@ -60,14 +68,16 @@ where
 impl<Tx, T, U, D, const BUF_LEN: usize, const URC_CAPACITY: usize>
     ClientBuilder<Tx, T, U, D, BUF_LEN, URC_CAPACITY>
 where
-    Tx: embedded_hal::serial::Write<u8>,
-    T: embedded_hal::timer::CountDown,
-    T::Time: From<u32>,
+    Tx: Transport,
+    T: Clock,
     U: UrcMatcher,
     D: Digester,
 {
     /// Use a custom [`UrcMatcher`] implementation.
     ///
+    /// Several matchers can be combined by passing them as a tuple, eg.
+    /// `(SocketUrcMatcher, GnssUrcMatcher)`.
+    ///
     /// [`UrcMatcher`]: trait.UrcMatcher.html
     pub fn with_custom_urc_matcher<U2: UrcMatcher>(
         self,
@@ -75,10 +85,12 @@ where
     ) -> ClientBuilder<Tx, T, U2, D, BUF_LEN, URC_CAPACITY> {
         ClientBuilder {
             serial_tx: self.serial_tx,
-            timer: self.timer,
+            clock: self.clock,
             config: self.config,
             custom_urc_matcher: matcher,
             custom_digester: self.custom_digester,
+            observer: self.observer,
+            payload_sink: self.payload_sink,
         }
     }
 
@@ -92,12 +104,39 @@ where
         ClientBuilder {
             custom_urc_matcher: self.custom_urc_matcher,
             serial_tx: self.serial_tx,
-            timer: self.timer,
+            clock: self.clock,
             config: self.config,
             custom_digester: digester,
+            observer: self.observer,
+            payload_sink: self.payload_sink,
         }
     }
 
+    /// Report every AT transaction to `observer`.
+    ///
+    /// See the [`observer`] module for the reported events.
+    ///
+    /// [`observer`]: observer/index.html
+    pub fn with_observer(mut self, observer: &'static dyn Observer) -> Self {
+        self.observer = Some(observer);
+        self
+    }
+
+    /// Stream the payloads of lines starting with any of `headers` to `sink`,
+    /// instead of buffering them.
+    ///
+    /// See the [`payload`] module for details.
+    ///
+    /// [`payload`]: payload/index.html
+    pub fn with_payload_sink(
+        mut self,
+        headers: &'static [PayloadHeader],
+        sink: &'static dyn PayloadSink,
+    ) -> Self {
+        self.payload_sink = Some((headers, sink));
+        self
+    }
+
     /// Set up and return a [`Client`] and [`IngressManager`] pair.
     ///
     /// [`Client`]: struct.Client.html
@@ -106,21 +145,32 @@ where
         self,
         queues: Queues<BUF_LEN, URC_CAPACITY>,
     ) -> ClientParser<Tx, T, U, D, BUF_LEN, URC_CAPACITY> {
-        let parser = IngressManager::with_customs(
+        let mut parser = IngressManager::with_customs(
             queues.res_queue.0,
             queues.urc_queue.0,
             queues.com_queue.1,
             self.custom_urc_matcher,
             self.custom_digester,
-        );
-        let client = Client::new(
+        )
+        .with_reset_markers(self.config.reset_markers);
+        if let Some(ms) = self.config.silence_timeout {
+            parser = parser.with_silence_timeout(ms);
+        }
+        if let Some((headers, sink)) = self.payload_sink {
+            parser = parser.with_payload_sink(headers, sink);
+        }
+        let mut client = Client::new(
             self.serial_tx,
             queues.res_queue.1,
             queues.urc_queue.1,
             queues.com_queue.0,
-            self.timer,
+            self.clock,
             self.config,
         );
+        if let Some(observer) = self.observer {
+            parser = parser.with_observer(observer);
+            client = client.with_observer(observer);
+        }
 
         (client, parser)
     }
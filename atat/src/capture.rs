@@ -0,0 +1,528 @@
+//! Recording and replaying of serial sessions, eg. to turn real modem traffic
+//! into regression tests.
+//!
+//! A capture is a text file with one chunk of data per line, preserving the
+//! chunk boundaries seen by the [`IngressManager`]:
+//!
+//! ```text
+//! # <millis> <rx|tx> <data>
+//! 0 tx AT+CGMR\r\n
+//! 12 rx AT+CGMR\r\r\n+CGMR: 1.2
+//! 15 rx \r\nOK\r\n
+//! ```
+//!
+//! The timestamp is `-` if unknown. In the data, backslashes, `\r` and `\n`
+//! are escaped as `\\`, `\r` and `\n`, and any other byte outside printable
+//! ASCII as `\xNN`. Empty lines and lines starting with `#` are ignored.
+//!
+//! Captures are recorded by an [`Observer`], formatting every [`Chunk`] built
+//! through [`Chunk::from_event`], eg. over RTT, or into a string through
+//! `Recorder` (feature `std`). A capture can then be fed back through an
+//! [`IngressManager`] with [`Capture::replay`], while the commands sent by a
+//! [`Client`] can be checked against it with [`ReplayTx`].
+//!
+//! [`IngressManager`]: ../struct.IngressManager.html
+//! [`Client`]: ../struct.Client.html
+//! [`Observer`]: ../observer/trait.Observer.html
+
+use core::fmt;
+use core::str::Lines;
+
+use crate::clock::Instant;
+use crate::digest::{DigestResult, Digester};
+use crate::observer::Event;
+use crate::queues::{ResConsumer, UrcConsumer};
+use crate::transport::Transport;
+use crate::urc_matcher::UrcMatcher;
+use crate::{IngressManager, InternalError};
+
+/// Error parsing a capture
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CaptureError {
+    /// Malformed line
+    Syntax,
+    /// Chunk did not fit in the provided buffer
+    Overflow,
+}
+
+/// Direction of a [`Chunk`], as seen from the host
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Direction {
+    /// Received from the device
+    Rx,
+    /// Sent to the device
+    Tx,
+}
+
+impl fmt::Display for Direction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Direction::Rx => f.write_str("rx"),
+            Direction::Tx => f.write_str("tx"),
+        }
+    }
+}
+
+/// A chunk of data sent or received, ie. a line of a capture.
+///
+/// The [`Display`] implementation formats the chunk as a capture line, without
+/// the trailing newline.
+///
+/// [`Display`]: https://doc.rust-lang.org/core/fmt/trait.Display.html
+#[derive(Debug, Clone, PartialEq)]
+pub struct Chunk<'a> {
+    pub at: Option<Instant>,
+    pub direction: Direction,
+    pub bytes: &'a [u8],
+}
+
+impl<'a> Chunk<'a> {
+    /// The chunk of a [`Event::DataReceived`] or [`Event::CommandSent`], if
+    /// any.
+    ///
+    /// [`Event::DataReceived`]: ../observer/enum.Event.html#variant.DataReceived
+    /// [`Event::CommandSent`]: ../observer/enum.Event.html#variant.CommandSent
+    #[must_use]
+    pub fn from_event(event: &Event<'a>) -> Option<Self> {
+        match *event {
+            Event::DataReceived { at, bytes } => Some(Self {
+                at,
+                direction: Direction::Rx,
+                bytes,
+            }),
+            Event::CommandSent { at, bytes } => Some(Self {
+                at,
+                direction: Direction::Tx,
+                bytes,
+            }),
+            _ => None,
+        }
+    }
+
+    /// Parse a capture line, decoding the data into `buf`.
+    ///
+    /// Returns `None` for empty lines and comments.
+    pub fn parse(line: &str, buf: &'a mut [u8]) -> Result<Option<Self>, CaptureError> {
+        let (at, direction, data) = match parse_line(line)? {
+            Some(line) => line,
+            None => return Ok(None),
+        };
+
+        let mut len = 0;
+        for b in data {
+            *buf.get_mut(len).ok_or(CaptureError::Overflow)? = b?;
+            len += 1;
+        }
+
+        Ok(Some(Self {
+            at,
+            direction,
+            bytes: &buf[..len],
+        }))
+    }
+}
+
+impl fmt::Display for Chunk<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.at {
+            Some(at) => write!(f, "{} {} ", at.as_millis(), self.direction)?,
+            None => write!(f, "- {} ", self.direction)?,
+        }
+        for &b in self.bytes {
+            match b {
+                b'\\' => f.write_str("\\\\")?,
+                b'\r' => f.write_str("\\r")?,
+                b'\n' => f.write_str("\\n")?,
+                0x20..=0x7e => write!(f, "{}", b as char)?,
+                _ => write!(f, "\\x{:02x}", b)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Split a capture line into its timestamp, direction and encoded data
+fn parse_line(
+    line: &str,
+) -> Result<Option<(Option<Instant>, Direction, Unescape<'_>)>, CaptureError> {
+    let line = line.strip_suffix('\r').unwrap_or(line);
+    if line.is_empty() || line.starts_with('#') {
+        return Ok(None);
+    }
+
+    let mut parts = line.splitn(3, ' ');
+    let at = match parts.next() {
+        Some("-") => None,
+        Some(ms) => Some(Instant::from_millis(
+            ms.parse().map_err(|_| CaptureError::Syntax)?,
+        )),
+        None => return Err(CaptureError::Syntax),
+    };
+    let direction = match parts.next() {
+        Some("rx") => Direction::Rx,
+        Some("tx") => Direction::Tx,
+        _ => return Err(CaptureError::Syntax),
+    };
+    let data = parts.next().unwrap_or("");
+
+    Ok(Some((at, direction, Unescape(data.as_bytes()))))
+}
+
+/// Iterator decoding the escaped data of a capture line
+struct Unescape<'a>(&'a [u8]);
+
+impl Iterator for Unescape<'_> {
+    type Item = Result<u8, CaptureError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let (&c, rest) = self.0.split_first()?;
+        self.0 = rest;
+        if c != b'\\' {
+            return Some(Ok(c));
+        }
+
+        let (&escaped, rest) = match self.0.split_first() {
+            Some(split) => split,
+            None => return Some(Err(CaptureError::Syntax)),
+        };
+        self.0 = rest;
+        Some(match escaped {
+            b'\\' => Ok(b'\\'),
+            b'r' => Ok(b'\r'),
+            b'n' => Ok(b'\n'),
+            b'x' if self.0.len() >= 2 => {
+                let (hex, rest) = self.0.split_at(2);
+                self.0 = rest;
+                core::str::from_utf8(hex)
+                    .ok()
+                    .filter(|hex| hex.bytes().all(|c| c.is_ascii_hexdigit()))
+                    .and_then(|hex| u8::from_str_radix(hex, 16).ok())
+                    .ok_or(CaptureError::Syntax)
+            }
+            _ => Err(CaptureError::Syntax),
+        })
+    }
+}
+
+/// A recorded serial session.
+///
+/// Example:
+/// ```ignore
+/// static mut RES_Q: ResQueue<256> = Queue::new();
+/// // ...
+/// let mut ingress = IngressManager::new(res_p, urc_p, com_c);
+///
+/// let capture = Capture::new(include_str!("cgmr.capture"));
+/// let mut results = Vec::new();
+/// capture.replay(&mut ingress, &mut res_c, &mut urc_c, |res| results.push(res))?;
+/// assert_eq!(results, [DigestResult::Response(Ok(Vec::from_slice(b"+CGMR: 1.2").unwrap()))]);
+/// ```
+#[derive(Debug, Clone, Copy)]
+pub struct Capture<'a>(&'a str);
+
+impl<'a> Capture<'a> {
+    #[must_use]
+    pub const fn new(text: &'a str) -> Self {
+        Self(text)
+    }
+
+    /// Feed every received chunk through `ingress`, calling `f` with each
+    /// response and URC.
+    ///
+    /// Each chunk is written and digested on its own, using its timestamp if
+    /// known, after which the response and URC queues are drained. URCs
+    /// completed by a chunk are thus reported before its response.
+    pub fn replay<D, U, F, const BUF_LEN: usize, const URC_CAPACITY: usize>(
+        &self,
+        ingress: &mut IngressManager<D, U, BUF_LEN, URC_CAPACITY>,
+        res_c: &mut ResConsumer<BUF_LEN>,
+        urc_c: &mut UrcConsumer<BUF_LEN, URC_CAPACITY>,
+        mut f: F,
+    ) -> Result<(), CaptureError>
+    where
+        D: Digester,
+        U: UrcMatcher,
+        F: FnMut(DigestResult<BUF_LEN>),
+    {
+        let mut buf = [0; BUF_LEN];
+        for line in self.0.lines() {
+            let chunk = match Chunk::parse(line, &mut buf)? {
+                Some(chunk) if chunk.direction == Direction::Rx => chunk,
+                _ => continue,
+            };
+
+            match chunk.at {
+                Some(at) => {
+                    ingress.write_at(chunk.bytes, at);
+                    ingress.digest_at(at);
+                }
+                None => {
+                    ingress.write(chunk.bytes);
+                    ingress.digest();
+                }
+            }
+
+            while let Some(urc) = urc_c.dequeue() {
+                f(DigestResult::Urc(urc));
+            }
+            while let Some(resp) = res_c.dequeue() {
+                f(match resp {
+                    Err(InternalError::Prompt) => DigestResult::Prompt,
+                    resp => DigestResult::Response(resp),
+                });
+            }
+        }
+        Ok(())
+    }
+
+    /// A mock [`Transport`] expecting exactly the sent chunks of the capture
+    ///
+    /// [`Transport`]: ../transport/trait.Transport.html
+    #[must_use]
+    pub fn tx(&self) -> ReplayTx<'a> {
+        ReplayTx {
+            lines: self.0.lines(),
+            data: Unescape(&[]),
+        }
+    }
+}
+
+/// Error returned by [`ReplayTx`] on data not matching the capture
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReplayError {
+    /// The sent data differs from the capture
+    Mismatch,
+    /// The capture is malformed
+    Capture(CaptureError),
+}
+
+/// Mock [`Transport`] checking that the sent data matches the sent chunks of a
+/// [`Capture`], regardless of how it is chunked.
+///
+/// Created through [`Capture::tx`].
+///
+/// [`Transport`]: ../transport/trait.Transport.html
+pub struct ReplayTx<'a> {
+    lines: Lines<'a>,
+    data: Unescape<'a>,
+}
+
+impl ReplayTx<'_> {
+    /// Whether all sent chunks of the capture have been written
+    pub fn is_done(&mut self) -> bool {
+        self.next_byte().is_none()
+    }
+
+    fn next_byte(&mut self) -> Option<Result<u8, CaptureError>> {
+        loop {
+            if let Some(b) = self.data.next() {
+                return Some(b);
+            }
+            match parse_line(self.lines.next()?) {
+                Ok(Some((_, Direction::Tx, data))) => self.data = data,
+                Ok(_) => {}
+                Err(e) => return Some(Err(e)),
+            }
+        }
+    }
+}
+
+impl Transport for ReplayTx<'_> {
+    type Error = ReplayError;
+
+    fn write_all(&mut self, buf: &[u8]) -> Result<(), Self::Error> {
+        for &c in buf {
+            match self.next_byte() {
+                Some(Ok(expected)) if expected == c => {}
+                Some(Err(e)) => return Err(ReplayError::Capture(e)),
+                _ => return Err(ReplayError::Mismatch),
+            }
+        }
+        Ok(())
+    }
+
+    fn flush(&mut self) -> Result<(), Self::Error> {
+        Ok(())
+    }
+}
+
+/// [`Observer`] recording a capture into a string.
+///
+/// As observers must be `'static`, the recorder is usually leaked:
+/// ```
+/// use atat::capture::Recorder;
+///
+/// let recorder: &'static Recorder = Box::leak(Box::new(Recorder::new()));
+/// // builder.with_observer(recorder)
+/// ```
+///
+/// [`Observer`]: ../observer/trait.Observer.html
+#[cfg(feature = "std")]
+#[derive(Debug, Default)]
+pub struct Recorder(std::sync::Mutex<std::string::String>);
+
+#[cfg(feature = "std")]
+impl Recorder {
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Take the capture recorded so far
+    pub fn take(&self) -> std::string::String {
+        core::mem::take(&mut *self.0.lock().unwrap())
+    }
+}
+
+#[cfg(feature = "std")]
+impl crate::observer::Observer for Recorder {
+    fn on_event(&self, event: &Event<'_>) {
+        use core::fmt::Write;
+
+        if let Some(chunk) = Chunk::from_event(event) {
+            writeln!(self.0.lock().unwrap(), "{}", chunk).ok();
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::observer::Observer;
+    use crate::queues::{ComQueue, ResQueue, UrcQueue};
+    use crate::InternalError;
+    use heapless::{spsc::Queue, Vec};
+
+    const TEST_RX_BUF_LEN: usize = 256;
+    const TEST_URC_CAPACITY: usize = 10;
+
+    #[test]
+    fn format() {
+        let chunk = Chunk {
+            at: Some(Instant::from_millis(12)),
+            direction: Direction::Rx,
+            bytes: b"+CGMR: \"1\\2\"\r\n\x00\xff",
+        };
+        let line = format!("{}", chunk);
+        assert_eq!(line, r#"12 rx +CGMR: "1\\2"\r\n\x00\xff"#);
+
+        let mut buf = [0; 32];
+        assert_eq!(Chunk::parse(&line, &mut buf), Ok(Some(chunk)));
+
+        let mut buf = [0; 4];
+        assert_eq!(
+            Chunk::parse("- tx AT\\r\\n", &mut buf),
+            Ok(Some(Chunk {
+                at: None,
+                direction: Direction::Tx,
+                bytes: b"AT\r\n",
+            }))
+        );
+        assert_eq!(
+            Chunk::parse("0 tx AT+CGMR", &mut buf),
+            Err(CaptureError::Overflow)
+        );
+        assert_eq!(Chunk::parse("# comment", &mut buf), Ok(None));
+        assert_eq!(
+            Chunk::parse("0 rx \\x4", &mut buf),
+            Err(CaptureError::Syntax)
+        );
+        assert_eq!(Chunk::parse("0 up AT", &mut buf), Err(CaptureError::Syntax));
+    }
+
+    /// Observer recording a capture, as done on a device
+    struct TestRecorder(std::sync::Mutex<std::string::String>);
+
+    impl Observer for TestRecorder {
+        fn on_event(&self, event: &Event<'_>) {
+            use core::fmt::Write;
+
+            if let Some(chunk) = Chunk::from_event(event) {
+                writeln!(self.0.lock().unwrap(), "{}", chunk).unwrap();
+            }
+        }
+    }
+
+    #[test]
+    fn record_replay() {
+        static RECORDER: TestRecorder =
+            TestRecorder(std::sync::Mutex::new(std::string::String::new()));
+
+        static mut RES_Q: ResQueue<TEST_RX_BUF_LEN> = Queue::new();
+        let (res_p, mut res_c) = unsafe { RES_Q.split() };
+        static mut URC_Q: UrcQueue<TEST_RX_BUF_LEN, TEST_URC_CAPACITY> = Queue::new();
+        let (urc_p, mut urc_c) = unsafe { URC_Q.split() };
+        static mut COM_Q: ComQueue = Queue::new();
+        let (_com_p, com_c) = unsafe { COM_Q.split() };
+
+        let mut ingress = IngressManager::new(res_p, urc_p, com_c).with_observer(&RECORDER);
+
+        let mut live = std::vec::Vec::new();
+        let mut session = |data: &[u8], ms| {
+            ingress.write_at(data, Instant::from_millis(ms));
+            ingress.digest_at(Instant::from_millis(ms));
+            while let Some(urc) = urc_c.dequeue() {
+                live.push(DigestResult::Urc(urc));
+            }
+            while let Some(resp) = res_c.dequeue() {
+                live.push(DigestResult::Response(resp));
+            }
+        };
+        RECORDER.on_event(&Event::CommandSent {
+            at: Some(Instant::from_millis(0)),
+            bytes: b"AT+CGMR\r\n",
+        });
+        session(b"AT+CGMR\r\r\n+CG", 10);
+        session(b"MR: 1.2\r\nOK\r\n+CREG: 1\r\n", 20);
+        session(b"AT\r\r\nERROR\r\n", 30);
+
+        let capture = RECORDER.0.lock().unwrap().clone();
+        assert_eq!(
+            capture,
+            "0 tx AT+CGMR\\r\\n\n\
+             10 rx AT+CGMR\\r\\r\\n+CG\n\
+             20 rx MR: 1.2\\r\\nOK\\r\\n+CREG: 1\\r\\n\n\
+             30 rx AT\\r\\r\\nERROR\\r\\n\n"
+        );
+        assert_eq!(
+            live,
+            [
+                DigestResult::Urc(Vec::from_slice(b"+CREG: 1").unwrap()),
+                DigestResult::Response(Ok(Vec::from_slice(b"+CGMR: 1.2").unwrap())),
+                DigestResult::Response(Err(InternalError::Error(
+                    Vec::from_slice(b"ERROR").unwrap()
+                ))),
+            ]
+        );
+
+        static mut REPLAY_RES_Q: ResQueue<TEST_RX_BUF_LEN> = Queue::new();
+        let (res_p, mut res_c) = unsafe { REPLAY_RES_Q.split() };
+        static mut REPLAY_URC_Q: UrcQueue<TEST_RX_BUF_LEN, TEST_URC_CAPACITY> = Queue::new();
+        let (urc_p, mut urc_c) = unsafe { REPLAY_URC_Q.split() };
+        static mut REPLAY_COM_Q: ComQueue = Queue::new();
+        let (_com_p, com_c) = unsafe { REPLAY_COM_Q.split() };
+
+        let mut ingress = IngressManager::new(res_p, urc_p, com_c);
+        let mut replayed = std::vec::Vec::new();
+        Capture::new(&capture)
+            .replay(&mut ingress, &mut res_c, &mut urc_c, |res| {
+                replayed.push(res)
+            })
+            .unwrap();
+        assert_eq!(replayed, live);
+    }
+
+    #[test]
+    fn replay_tx() {
+        let capture = Capture::new("0 tx AT+CG\n1 rx AT\n2 tx MR\\r\\n\n\n3 tx AT\\r\\n\n");
+
+        let mut tx = capture.tx();
+        assert_eq!(tx.write_all(b"AT"), Ok(()));
+        assert_eq!(tx.write_all(b"+CGMR\r"), Ok(()));
+        assert_eq!(tx.write_all(b"\nAT\r\n"), Ok(()));
+        assert!(tx.is_done());
+
+        let mut tx = capture.tx();
+        assert_eq!(tx.write_all(b"AT+CFUN"), Err(ReplayError::Mismatch));
+    }
+}
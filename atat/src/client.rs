@@ -1,15 +1,18 @@
-use embedded_hal::{serial, timer::CountDown};
-
 use crate::atat_log;
-use crate::error::Error;
+use crate::clock::{Clock, Instant};
+use crate::error::{Error, InternalError};
 use crate::helpers::LossyStr;
+use crate::observer::{Event, Observer};
 use crate::queues::{ComProducer, ResConsumer, UrcConsumer, RES_CAPACITY};
-use crate::traits::{AtatClient, AtatCmd, AtatUrc};
+use crate::traits::{AtatClient, AtatCmd, AtatDataClient, AtatDataCmd, AtatUrc};
+use crate::transport::Transport;
 use crate::{Command, Config};
 
 #[derive(Debug, PartialEq)]
 enum ClientState {
     Idle,
+    /// Awaiting the prompt for the payload of an `AtatDataCmd`
+    AwaitingPrompt,
     AwaitingResponse,
 }
 
@@ -31,8 +34,8 @@ pub enum Mode {
 /// `reset` to the ingress-manager.
 pub struct Client<Tx, T, const BUF_LEN: usize, const URC_CAPACITY: usize>
 where
-    Tx: serial::Write<u8>,
-    T: CountDown,
+    Tx: Transport,
+    T: Clock,
 {
     /// Serial writer
     tx: Tx,
@@ -45,22 +48,39 @@ where
     com_p: ComProducer,
 
     state: ClientState,
-    timer: T,
+    clock: T,
+    /// Time of the last response or URC, used for the command cooldown
+    last_receive: Option<Instant>,
+    /// Time of the last sent command, used to detect an idle link
+    last_send: Option<Instant>,
+    /// Whether the link was woken up before sending the current command, in
+    /// which case it is retried if the device dropped it
+    woken: bool,
+    /// Deadline of the command currently awaiting a response
+    deadline: Option<Instant>,
+    /// Time to check whether the device dropped the current command, as it
+    /// was sent after a wakeup
+    wake_check: Option<Instant>,
+    /// Called when the device restarted, eg. to rerun an init sequence
+    reset_handler: Option<fn(&mut Self)>,
+    /// Whether the device restarted since the reset handler was last called
+    reset_pending: bool,
+    /// Observer of sent commands and timeouts
+    observer: Option<&'static dyn Observer>,
     config: Config,
 }
 
 impl<Tx, T, const BUF_LEN: usize, const URC_CAPACITY: usize> Client<Tx, T, BUF_LEN, URC_CAPACITY>
 where
-    Tx: serial::Write<u8>,
-    T: CountDown,
-    T::Time: From<u32>,
+    Tx: Transport,
+    T: Clock,
 {
     pub fn new(
         tx: Tx,
         res_c: ResConsumer<BUF_LEN>,
         urc_c: UrcConsumer<BUF_LEN, URC_CAPACITY>,
         com_p: ComProducer,
-        timer: T,
+        clock: T,
         config: Config,
     ) -> Self {
         Self {
@@ -70,52 +90,345 @@ where
             com_p,
             state: ClientState::Idle,
             config,
-            timer,
+            clock,
+            last_receive: None,
+            last_send: None,
+            woken: false,
+            deadline: None,
+            wake_check: None,
+            reset_handler: None,
+            reset_pending: false,
+            observer: None,
         }
     }
-}
 
-impl<Tx, T, const BUF_LEN: usize, const URC_CAPACITY: usize> AtatClient
-    for Client<Tx, T, BUF_LEN, URC_CAPACITY>
-where
-    Tx: serial::Write<u8>,
-    T: CountDown,
-    T::Time: From<u32>,
-{
-    fn send<A: AtatCmd<LEN>, const LEN: usize>(
+    /// Report sent commands and timeouts to `observer`
+    #[must_use]
+    pub fn with_observer(mut self, observer: &'static dyn Observer) -> Self {
+        self.observer = Some(observer);
+        self
+    }
+
+    fn observe(&self, event: Event<'_>) {
+        if let Some(observer) = self.observer {
+            observer.on_event(&event);
+        }
+    }
+
+    /// Register a handler called when the device restarts, as detected by
+    /// the reset markers in [`Config::reset_markers`]. This can be used to
+    /// rerun the initialization sequence of the device.
+    ///
+    /// The handler is called on the next call to [`send`] after the reset,
+    /// before the command is sent. A command awaiting a response while the
+    /// device restarts fails with [`Error::DeviceReset`].
+    ///
+    /// [`Config::reset_markers`]: struct.Config.html#method.reset_markers
+    /// [`send`]: trait.AtatClient.html#tymethod.send
+    /// [`Error::DeviceReset`]: enum.Error.html#variant.DeviceReset
+    pub fn on_device_reset(&mut self, handler: fn(&mut Self)) {
+        self.reset_handler = Some(handler);
+    }
+
+    /// Run the reset handler, if any. Resets while running the handler do
+    /// not trigger it again.
+    fn device_reset(&mut self) {
+        self.reset_pending = false;
+        if let Some(handler) = self.reset_handler.take() {
+            handler(self);
+            self.reset_handler = Some(handler);
+        }
+    }
+
+    /// Mark the time of a received response or URC, delaying the next
+    /// command by `cmd_cooldown`
+    fn received(&mut self) {
+        self.last_receive = Some(self.clock.now());
+    }
+
+    /// Wake the device if the link has been idle for longer than
+    /// `wake_after_idle`, returning whether a wakeup was performed
+    fn wake_if_idle<E>(&mut self) -> Result<bool, Error<E>> {
+        let threshold = match self.config.wake_after_idle {
+            Some(threshold) => threshold,
+            None => return Ok(false),
+        };
+        let now = self.clock.now();
+        let idle = match self.last_send.max(self.last_receive) {
+            Some(last_activity) => now.millis_since(last_activity) >= u64::from(threshold),
+            None => true,
+        };
+        if idle {
+            self.wake()?;
+        }
+        Ok(idle)
+    }
+
+    /// Assert the wake signal of the transport and send the wakeup prefix,
+    /// followed by the wakeup delay
+    fn wake<E>(&mut self) -> Result<(), Error<E>> {
+        atat_log!(debug, "Waking up device");
+        self.tx.wake().map_err(|_e| Error::Write)?;
+        if !self.config.wake_prefix.is_empty() {
+            self.tx
+                .write_all(self.config.wake_prefix)
+                .map_err(|_e| Error::Write)?;
+            self.tx.flush().map_err(|_e| Error::Write)?;
+        }
+        let ready = self.clock.now().add_millis(self.config.wake_delay);
+        while self.clock.now() < ready {}
+        Ok(())
+    }
+
+    /// Check that the response to `A` fits in the ingress buffer, if its
+    /// maximum length is known
+    fn check_response_len<A: AtatCmd<LEN>, const LEN: usize>() {
+        if let Some(len) = A::MAX_RESPONSE_LEN {
+            debug_assert!(
+                len <= BUF_LEN,
+                "Response of up to {} bytes does not fit in ingress buffer of {} bytes",
+                len,
+                BUF_LEN
+            );
+        }
+    }
+
+    /// Handle any device reset, and wait out the command cooldown and wakeup
+    /// before sending a new command. With `force_wake`, the device is woken
+    /// up even if the link is not idle, eg. to retry a command.
+    fn prepare_send<E>(&mut self, force_wake: bool) -> Result<(), Error<E>> {
+        // Handle the device restarting while idle, and drop the result of a
+        // late wakeup check
+        loop {
+            match self.res_c.peek() {
+                Some(Err(InternalError::DeviceReset)) => self.reset_pending = true,
+                Some(Err(InternalError::NothingReceived)) => {}
+                _ => break,
+            }
+            self.res_c.dequeue();
+        }
+        if self.reset_pending {
+            self.device_reset();
+        }
+
+        // compare the time of the last response or URC and ensure at least
+        // `self.config.cmd_cooldown` ms have passed before sending a new
+        // command
+        if let Some(last_receive) = self.last_receive {
+            let ready = last_receive.add_millis(self.config.cmd_cooldown);
+            while self.clock.now() < ready {}
+        }
+
+        self.woken = if force_wake {
+            self.wake()?;
+            true
+        } else {
+            self.wake_if_idle()?
+        };
+        Ok(())
+    }
+
+    /// Send `cmd` to the device, and start awaiting the response
+    fn write_cmd<A: AtatCmd<LEN>, const LEN: usize>(
         &mut self,
         cmd: &A,
-    ) -> nb::Result<A::Response, Error<A::Error>> {
-        if let ClientState::Idle = self.state {
-            if A::FORCE_RECEIVE_STATE && self.com_p.enqueue(Command::ForceReceiveState).is_err() {
+    ) -> Result<(), Error<A::Error>> {
+        if A::FORCE_RECEIVE_STATE && self.com_p.enqueue(Command::ForceReceiveState).is_err() {
+            // TODO: Consider how to act in this situation.
+            atat_log!(
+                error,
+                "Failed to signal parser to force state transition to 'ReceivingResponse'!"
+            );
+        }
+
+        // Stream the command straight to the serial port, to avoid
+        // staging it on the stack
+        let at = self.observer.map(|_| self.clock.now());
+        let observer = self.observer;
+        let tx = &mut self.tx;
+        let mut len = 0;
+        let written = cmd
+            .write_to(|chunk| {
+                atat_log!(trace, "Sending: \"{:?}\"", LossyStr(chunk));
+                if let Some(observer) = observer {
+                    observer.on_event(&Event::CommandSent { at, bytes: chunk });
+                }
+                tx.write_all(chunk).map_err(|_e| Error::Write)?;
+                len += chunk.len();
+                Ok(())
+            })
+            .and_then(|_| self.tx.flush().map_err(|_e| Error::Write));
+        if let Err(e) = written {
+            // Discard the echo of the partially written command, and undo
+            // forcing the receive state
+            if len > 0 || A::FORCE_RECEIVE_STATE {
+                atat_log!(error, "Failed to write command after {} bytes", len);
+                if self.com_p.enqueue(Command::Reset).is_err() {
+                    // TODO: Consider how to act in this situation.
+                    atat_log!(
+                        error,
+                        "Failed to signal parser to clear buffer on write error!"
+                    );
+                }
+            }
+            return Err(e);
+        }
+        atat_log!(debug, "Sent command ({} bytes)", len);
+
+        let now = self.clock.now();
+        self.last_send = Some(now);
+        self.deadline = Some(now.add_millis(A::MAX_TIMEOUT_MS));
+        self.wake_check = if self.woken {
+            Some(now.add_millis(self.config.wake_timeout))
+        } else {
+            None
+        };
+        self.state = ClientState::AwaitingResponse;
+        Ok(())
+    }
+
+    /// Send the payload of `cmd`, followed by its terminator, and start
+    /// awaiting the final response
+    fn write_payload<A: AtatDataCmd<LEN>, const LEN: usize>(
+        &mut self,
+        cmd: &A,
+    ) -> Result<(), Error<A::Error>> {
+        // The final response is not preceded by an echo
+        if self.com_p.enqueue(Command::ForceReceiveState).is_err() {
+            // TODO: Consider how to act in this situation.
+            atat_log!(
+                error,
+                "Failed to signal parser to force state transition to 'ReceivingResponse'!"
+            );
+        }
+
+        let at = self.observer.map(|_| self.clock.now());
+        let payload = cmd.payload();
+        self.observe(Event::CommandSent { at, bytes: payload });
+        let mut written = self.tx.write_all(payload);
+        if let Some(terminator) = A::PAYLOAD_TERMINATOR.filter(|_| written.is_ok()) {
+            let terminator = [terminator];
+            self.observe(Event::CommandSent {
+                at,
+                bytes: &terminator,
+            });
+            written = self.tx.write_all(&terminator);
+        }
+        if written.and_then(|_| self.tx.flush()).is_err() {
+            // Undo forcing the receive state, and abandon the command
+            atat_log!(error, "Failed to write payload");
+            if self.com_p.enqueue(Command::Reset).is_err() {
                 // TODO: Consider how to act in this situation.
                 atat_log!(
                     error,
-                    "Failed to signal parser to force state transition to 'ReceivingResponse'!"
+                    "Failed to signal parser to clear buffer on write error!"
                 );
             }
+            self.state = ClientState::Idle;
+            return Err(Error::Write);
+        }
+        atat_log!(debug, "Sent payload ({} bytes)", payload.len());
 
-            // compare the time of the last response or URC and ensure at least
-            // `self.config.cmd_cooldown` ms have passed before sending a new
-            // command
-            nb::block!(self.timer.try_wait()).ok();
-            let cmd_buf = cmd.as_bytes();
+        let now = self.clock.now();
+        self.last_send = Some(now);
+        self.deadline = Some(now.add_millis(A::MAX_TIMEOUT_MS));
+        self.state = ClientState::AwaitingResponse;
+        Ok(())
+    }
 
-            if cmd_buf.len() < 50 {
-                atat_log!(debug, "Sending command: \"{:?}\"", LossyStr(&cmd_buf));
-            } else {
-                atat_log!(
-                    debug,
-                    "Sending command with too long payload ({} bytes) to log!",
-                    cmd_buf.len()
-                );
+    /// Check for the prompt of `cmd`, sending the payload once it is
+    /// received. Error responses, and device resets, are left for
+    /// `check_response` to handle, while any other response fails the command
+    /// without sending the payload.
+    fn check_prompt<A: AtatDataCmd<LEN>, const LEN: usize>(
+        &mut self,
+        cmd: &A,
+    ) -> nb::Result<(), Error<A::Error>> {
+        match self.res_c.peek() {
+            Some(Err(InternalError::Prompt)) => {
+                self.res_c.dequeue();
+                atat_log!(trace, "Received prompt");
+                self.write_payload(cmd).map_err(nb::Error::Other)
             }
+            Some(Ok(_)) => {
+                self.res_c.dequeue();
+                atat_log!(error, "Received response instead of prompt");
+                self.received();
+                self.state = ClientState::Idle;
+                Err(nb::Error::Other(Error::InvalidResponse))
+            }
+            Some(Err(_)) => {
+                self.state = ClientState::AwaitingResponse;
+                Ok(())
+            }
+            None => {
+                if self.timed_out() {
+                    Err(nb::Error::Other(Error::Timeout))
+                } else {
+                    Err(nb::Error::WouldBlock)
+                }
+            }
+        }
+    }
 
-            for c in cmd_buf {
-                nb::block!(self.tx.try_write(c)).map_err(|_e| Error::Write)?;
+    fn check_data_response<A: AtatDataCmd<LEN>, const LEN: usize>(
+        &mut self,
+        cmd: &A,
+    ) -> nb::Result<A::Response, Error<A::Error>> {
+        if let ClientState::AwaitingPrompt = self.state {
+            self.check_prompt(cmd)?;
+        }
+        self.check_response(cmd)
+    }
+
+    /// Whether the deadline of the current command has passed, in which case
+    /// the command is abandoned. Only applies in `Mode::Timeout`.
+    ///
+    /// After the wake timeout of a command sent after a wakeup, the ingress
+    /// manager is asked whether anything was received, to retry the command
+    /// early if the device dropped it.
+    fn timed_out(&mut self) -> bool {
+        if let Mode::Timeout = self.config.mode {
+            let now = self.clock.now();
+            if matches!(self.wake_check, Some(check) if now >= check) {
+                self.wake_check = None;
+                if self.com_p.enqueue(Command::CheckReceived).is_err() {
+                    // TODO: Consider how to act in this situation.
+                    atat_log!(error, "Failed to signal parser to check for the echo!");
+                }
+            }
+            if matches!(self.deadline, Some(deadline) if now >= deadline) {
+                // The device received the command, but never responded
+                self.woken = false;
+                self.state = ClientState::Idle;
+                // Tell the parser to reset to initial state due to timeout,
+                // reporting `Event::Timeout` with any partial response
+                if self.com_p.enqueue(Command::Timeout).is_err() {
+                    // TODO: Consider how to act in this situation.
+                    atat_log!(error, "Failed to signal parser to clear buffer on timeout!");
+                }
+                return true;
             }
-            nb::block!(self.tx.try_flush()).map_err(|_e| Error::Write)?;
-            self.state = ClientState::AwaitingResponse;
+        }
+        false
+    }
+}
+
+impl<Tx, T, const BUF_LEN: usize, const URC_CAPACITY: usize> AtatClient
+    for Client<Tx, T, BUF_LEN, URC_CAPACITY>
+where
+    Tx: Transport,
+    T: Clock,
+{
+    fn send<A: AtatCmd<LEN>, const LEN: usize>(
+        &mut self,
+        cmd: &A,
+    ) -> nb::Result<A::Response, Error<A::Error>> {
+        if let ClientState::Idle = self.state {
+            Self::check_response_len::<A, LEN>();
+
+            self.prepare_send(false).map_err(nb::Error::Other)?;
+            self.write_cmd(cmd).map_err(nb::Error::Other)?;
         }
 
         if !A::EXPECTS_RESPONSE_CODE {
@@ -126,16 +439,25 @@ where
         match self.config.mode {
             Mode::Blocking => Ok(nb::block!(self.check_response(cmd))?),
             Mode::NonBlocking => self.check_response(cmd),
-            Mode::Timeout => {
-                self.timer.try_start(A::MAX_TIMEOUT_MS).ok();
-                Ok(nb::block!(self.check_response(cmd))?)
-            }
+            Mode::Timeout => match nb::block!(self.check_response(cmd)) {
+                // The device dropped the command while waking up
+                Err(Error::Timeout) if self.woken => {
+                    atat_log!(warn, "Nothing received after wakeup, retrying");
+                    self.prepare_send(true).map_err(nb::Error::Other)?;
+                    self.woken = false;
+                    self.write_cmd(cmd).map_err(nb::Error::Other)?;
+                    Ok(nb::block!(self.check_response(cmd))?)
+                }
+                result => Ok(result?),
+            },
         }
     }
 
     fn peek_urc_with<URC: AtatUrc, F: FnOnce(URC::Response) -> bool>(&mut self, f: F) {
+        if self.urc_c.ready() {
+            self.received();
+        }
         if let Some(urc) = self.urc_c.peek() {
-            self.timer.try_start(self.config.cmd_cooldown).ok();
             if let Some(urc) = URC::parse(urc) {
                 if !f(urc) {
                     return;
@@ -151,13 +473,37 @@ where
         &mut self,
         cmd: &A,
     ) -> nb::Result<A::Response, Error<A::Error>> {
-        if let Some(result) = self.res_c.dequeue() {
+        if let Some(Err(InternalError::DeviceReset)) = self.res_c.peek() {
+            self.res_c.dequeue();
+            atat_log!(warn, "Device reset while awaiting response");
+            self.received();
+            self.reset_pending = true;
+            self.state = ClientState::Idle;
+            return Err(nb::Error::Other(Error::DeviceReset));
+        }
+
+        if let Some(Err(InternalError::NothingReceived)) = self.res_c.peek() {
+            self.res_c.dequeue();
+            // Ignore late results of wakeup checks for previous commands
+            if self.woken {
+                atat_log!(warn, "Nothing received after wakeup");
+                self.state = ClientState::Idle;
+                return Err(nb::Error::Other(Error::Timeout));
+            }
+        }
+
+        if let Some(mut result) = self.res_c.dequeue() {
+            // Commands sent without a data phase take a prompt as an empty
+            // response
+            if let Err(InternalError::Prompt) = result {
+                result = Ok(heapless::Vec::new());
+            }
             return cmd
                 .parse(result.as_deref())
                 .map_err(nb::Error::from)
                 .and_then(|r| {
                     if let ClientState::AwaitingResponse = self.state {
-                        self.timer.try_start(self.config.cmd_cooldown).ok();
+                        self.received();
                         self.state = ClientState::Idle;
                         Ok(r)
                     } else {
@@ -167,20 +513,12 @@ where
                     }
                 })
                 .map_err(|e| {
-                    self.timer.try_start(self.config.cmd_cooldown).ok();
+                    self.received();
                     self.state = ClientState::Idle;
                     e
                 });
-        } else if let Mode::Timeout = self.config.mode {
-            if self.timer.try_wait().is_ok() {
-                self.state = ClientState::Idle;
-                // Tell the parser to reset to initial state due to timeout
-                if self.com_p.enqueue(Command::Reset).is_err() {
-                    // TODO: Consider how to act in this situation.
-                    atat_log!(error, "Failed to signal parser to clear buffer on timeout!");
-                }
-                return Err(nb::Error::Other(Error::Timeout));
-            }
+        } else if self.timed_out() {
+            return Err(nb::Error::Other(Error::Timeout));
         }
         Err(nb::Error::WouldBlock)
     }
@@ -208,6 +546,33 @@ where
     }
 }
 
+impl<Tx, T, const BUF_LEN: usize, const URC_CAPACITY: usize> AtatDataClient
+    for Client<Tx, T, BUF_LEN, URC_CAPACITY>
+where
+    Tx: Transport,
+    T: Clock,
+{
+    fn send_with_data<A: AtatDataCmd<LEN>, const LEN: usize>(
+        &mut self,
+        cmd: &A,
+    ) -> nb::Result<A::Response, Error<A::Error>> {
+        if let ClientState::Idle = self.state {
+            Self::check_response_len::<A, LEN>();
+
+            self.prepare_send(false).map_err(nb::Error::Other)?;
+            // The device might be waiting for the payload, so never retry
+            self.woken = false;
+            self.write_cmd(cmd).map_err(nb::Error::Other)?;
+            self.state = ClientState::AwaitingPrompt;
+        }
+
+        match self.config.mode {
+            Mode::NonBlocking => self.check_data_response(cmd),
+            Mode::Blocking | Mode::Timeout => Ok(nb::block!(self.check_data_response(cmd))?),
+        }
+    }
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
@@ -215,27 +580,22 @@ mod test {
     use crate::{self as atat, InternalError};
     use crate::{
         atat_derive::{AtatCmd, AtatEnum, AtatResp, AtatUrc},
-        GenericError,
+        GenericError, UrcMatcher, UrcMatcherResult,
     };
     use heapless::{spsc::Queue, String, Vec};
     use nb;
+    use serde_at::RangeList;
 
     const TEST_RX_BUF_LEN: usize = 256;
     const TEST_URC_CAPACITY: usize = 10;
 
-    struct CdMock;
+    /// Clock advancing 100 ms on every call
+    struct ClockMock(u64);
 
-    impl CountDown for CdMock {
-        type Error = core::convert::Infallible;
-        type Time = u32;
-        fn try_start<T>(&mut self, _count: T) -> Result<(), Self::Error>
-        where
-            T: Into<Self::Time>,
-        {
-            Ok(())
-        }
-        fn try_wait(&mut self) -> nb::Result<(), Self::Error> {
-            Ok(())
+    impl Clock for ClockMock {
+        fn now(&mut self) -> Instant {
+            self.0 += 100;
+            Instant::from_millis(self.0)
         }
     }
 
@@ -249,7 +609,7 @@ mod test {
         }
     }
 
-    impl serial::Write<u8> for TxMock {
+    impl embedded_hal::serial::Write<u8> for TxMock {
         type Error = ();
 
         fn try_write(&mut self, c: u8) -> nb::Result<(), Self::Error> {
@@ -282,7 +642,20 @@ mod test {
     }
 
     #[derive(Clone, AtatCmd)]
-    #[at_cmd("+CFUN", NoResponse, timeout_ms = 180000)]
+    #[at_cmd("+CSGT", NoResponse)]
+    pub struct SetGreetingText<'a> {
+        #[at_arg(position = 0, len = 4)]
+        pub text: &'a str,
+    }
+
+    #[derive(Clone, AtatCmd)]
+    #[at_cmd(
+        "+CFUN",
+        NoResponse,
+        timeout_ms = 180000,
+        read = ModuleFunctionality,
+        test = ModuleFunctionalityRanges
+    )]
     pub struct SetModuleFunctionality {
         #[at_arg(position = 0)]
         pub fun: Functionality,
@@ -323,11 +696,15 @@ mod test {
         pub rst: Option<ResetMode>,
     }
 
-    // #[derive(Clone, AtatCmd)]
-    // #[at_cmd("+CUN", TestResponseStringMixed, timeout_ms = 180000)]
-    // pub struct TestUnnamedStruct(Functionality, Option<ResetMode>);
+    #[derive(Clone, AtatCmd)]
+    #[at_cmd("+CUN", TestUnnamedResponse, timeout_ms = 180000)]
+    pub struct TestUnnamedStruct(Functionality, Option<ResetMode>);
 
-    #[derive(Clone, PartialEq, AtatEnum)]
+    #[derive(Clone, AtatCmd)]
+    #[at_cmd("+CUN", (u8, String<16>), timeout_ms = 180000)]
+    pub struct TestTupleRespCmd(Functionality);
+
+    #[derive(Clone, PartialEq, Debug, AtatEnum)]
     #[at_enum(u8)]
     pub enum Functionality {
         #[at_arg(value = 0)]
@@ -380,6 +757,20 @@ mod test {
         pub data: String<64>,
     }
 
+    #[derive(Clone, AtatResp, PartialEq, Debug)]
+    pub struct TestUnnamedResponse(u8, usize, String<64>);
+
+    #[derive(Clone, AtatResp, PartialEq, Debug)]
+    pub struct ModuleFunctionality {
+        pub fun: Functionality,
+    }
+
+    #[derive(Clone, AtatResp, PartialEq, Debug)]
+    pub struct ModuleFunctionalityRanges {
+        pub fun: RangeList<u8, 4>,
+        pub rst: RangeList<u8, 2>,
+    }
+
     #[derive(Clone, AtatResp)]
     pub struct MessageWaitingIndication {
         #[at_arg(position = 0)]
@@ -392,6 +783,23 @@ mod test {
     pub enum Urc {
         #[at_urc(b"+UMWI")]
         MessageWaitingIndication(MessageWaitingIndication),
+        #[at_urc(b"+UUSORD")]
+        SocketDataAvailable {
+            #[at_arg(position = 1)]
+            length: usize,
+            #[at_arg(position = 0)]
+            socket: u8,
+        },
+        #[at_urc(b"+CREG")]
+        NetworkRegistration(u8, Option<String<8>>),
+        #[at_urc(b"RING")]
+        Ring,
+        #[at_urc("^SYSSTART")]
+        SysStart,
+        #[at_urc("+USORF", payload_len_field = 3)]
+        SocketDataRead(u8, String<16>, u16, usize, String<16>),
+        #[at_urc("+QIURC", lines = 2)]
+        ReceivedData,
     }
 
     macro_rules! setup {
@@ -408,8 +816,8 @@ mod test {
             assert_eq!(com_p.capacity(), crate::queues::COM_CAPACITY);
 
             let tx_mock = TxMock::new(String::new());
-            let client: Client<TxMock, CdMock, TEST_RX_BUF_LEN, TEST_URC_CAPACITY> =
-                Client::new(tx_mock, res_c, urc_c, com_p, CdMock, $config);
+            let client: Client<TxMock, ClockMock, TEST_RX_BUF_LEN, TEST_URC_CAPACITY> =
+                Client::new(tx_mock, res_c, urc_c, com_p, ClockMock(0), $config);
             (client, res_p, urc_p)
         }};
     }
@@ -486,7 +894,96 @@ mod test {
     }
 
     #[test]
-    #[ignore]
+    fn serialize_error() {
+        let (mut client, mut p, _) = setup!(Config::new(Mode::Blocking));
+
+        // `text` is longer than the `len` attribute allows for
+        let cmd = SetGreetingText {
+            text: "Hello there",
+        };
+
+        assert!(matches!(cmd.try_as_bytes(), Err(Error::Serialize)));
+        assert_eq!(cmd.write(&mut [0; 16]), Err(Error::Serialize));
+
+        // Nothing is sent
+        assert_eq!(client.state, ClientState::Idle);
+        assert_eq!(client.send(&cmd), Err(nb::Error::Other(Error::Serialize)));
+        assert_eq!(client.state, ClientState::Idle);
+        assert_eq!(client.tx.s, String::<32>::new());
+
+        // The client streams commands within the length
+        let cmd = SetGreetingText { text: "Hi" };
+        p.enqueue(Ok(Vec::<u8, TEST_RX_BUF_LEN>::new())).unwrap();
+        assert_eq!(client.send(&cmd), Ok(NoResponse));
+        assert_eq!(client.tx.s, String::<32>::from("AT+CSGT=\"Hi\"\r\n"));
+    }
+
+    #[derive(Clone, AtatCmd)]
+    #[at_cmd("+CSGT", NoResponse, force_receive_state = true)]
+    pub struct ForceGreetingText<'a> {
+        #[at_arg(position = 0, len = 4)]
+        pub text: &'a str,
+    }
+
+    #[test]
+    fn serialize_error_force_receive_state() {
+        static mut RES_Q: queues::ResQueue<TEST_RX_BUF_LEN> = Queue::new();
+        let (_res_p, res_c) = unsafe { RES_Q.split() };
+        static mut URC_Q: queues::UrcQueue<TEST_RX_BUF_LEN, TEST_URC_CAPACITY> = Queue::new();
+        let (_urc_p, urc_c) = unsafe { URC_Q.split() };
+        static mut COM_Q: queues::ComQueue = Queue::new();
+        let (com_p, mut com_c) = unsafe { COM_Q.split() };
+
+        let mut client: Client<TxMock, ClockMock, TEST_RX_BUF_LEN, TEST_URC_CAPACITY> = Client::new(
+            TxMock::new(String::new()),
+            res_c,
+            urc_c,
+            com_p,
+            ClockMock(0),
+            Config::new(Mode::Blocking),
+        );
+
+        // Over-length command, failing before anything is written
+        let cmd = ForceGreetingText {
+            text: "Hello there",
+        };
+        assert_eq!(client.send(&cmd), Err(nb::Error::Other(Error::Serialize)));
+        assert_eq!(client.tx.s, String::<32>::new());
+
+        // The forced receive state is reset again
+        assert_eq!(com_c.dequeue(), Some(Command::ForceReceiveState));
+        assert_eq!(com_c.dequeue(), Some(Command::Reset));
+        assert_eq!(com_c.dequeue(), None);
+    }
+
+    #[test]
+    fn partial_write() {
+        let (mut client, _, _) = setup!(Config::new(Mode::Blocking));
+
+        // Leave room for part of the command only
+        for _ in 0..60 {
+            client.tx.s.push('x').unwrap();
+        }
+
+        let cmd = SetGreetingText { text: "Hi" };
+        assert_eq!(client.send(&cmd), Err(nb::Error::Other(Error::Write)));
+        assert_eq!(client.state, ClientState::Idle);
+    }
+
+    #[test]
+    fn write_to_slice() {
+        let cmd = SetModuleFunctionality {
+            fun: Functionality::APM,
+            rst: None,
+        };
+
+        let mut buf = [0; 32];
+        let len = cmd.write(&mut buf).unwrap();
+        assert_eq!(&buf[..len], b"AT+CFUN=4\r\n");
+        assert_eq!(cmd.write(&mut buf[..4]), Err(Error::Serialize));
+    }
+
+    #[test]
     fn countdown() {
         let (mut client, _, _) = setup!(Config::new(Mode::Timeout));
 
@@ -499,12 +996,233 @@ mod test {
         assert_eq!(client.send(&cmd), Err(nb::Error::Other(Error::Timeout)));
 
         match client.config.mode {
-            Mode::Timeout => {} // assert_eq!(cd_mock.time, 180000),
+            Mode::Timeout => assert!(client.clock.0 >= 180_000),
             _ => panic!("Wrong AT mode"),
         }
         assert_eq!(client.state, ClientState::Idle);
     }
 
+    #[test]
+    fn observer() {
+        struct Recorder(std::sync::Mutex<std::vec::Vec<std::string::String>>);
+
+        impl Observer for Recorder {
+            fn on_event(&self, event: &Event<'_>) {
+                let s = match event {
+                    Event::CommandSent { bytes, .. } => {
+                        format!("sent {}", core::str::from_utf8(bytes).unwrap())
+                    }
+                    e => format!("{:?}", e),
+                };
+                self.0.lock().unwrap().push(s);
+            }
+        }
+
+        static RECORDER: Recorder = Recorder(std::sync::Mutex::new(std::vec::Vec::new()));
+
+        let (client, _, _) = setup!(Config::new(Mode::Timeout));
+        let mut client = client.with_observer(&RECORDER);
+
+        let cmd = Test2Cmd {
+            fun: Functionality::DM,
+            rst: Some(ResetMode::Reset),
+        };
+        assert_eq!(client.send(&cmd), Err(nb::Error::Other(Error::Timeout)));
+
+        // Commands are streamed in chunks
+        let events = RECORDER.0.lock().unwrap();
+        let sent: std::string::String = events
+            .iter()
+            .filter_map(|e| e.strip_prefix("sent "))
+            .collect();
+        assert_eq!(sent, "AT+FUN=1,6\r\n");
+    }
+
+    #[test]
+    fn client_is_send() {
+        // The client is moved into RTIC resources, and other threads
+        fn assert_send<T: Send>() {}
+        assert_send::<Client<TxMock, ClockMock, TEST_RX_BUF_LEN, TEST_URC_CAPACITY>>();
+    }
+
+    #[test]
+    fn device_reset() {
+        let (mut client, mut p, _) = setup!(Config::new(Mode::NonBlocking));
+        client.on_device_reset(|client| {
+            client.tx.s.push_str("INIT;").unwrap();
+        });
+
+        let cmd = SetModuleFunctionality {
+            fun: Functionality::APM,
+            rst: Some(ResetMode::DontReset),
+        };
+        assert_eq!(client.send(&cmd), Err(nb::Error::WouldBlock));
+
+        // In-flight command fails
+        p.enqueue(Err(InternalError::DeviceReset)).unwrap();
+        assert_eq!(client.send(&cmd), Err(nb::Error::Other(Error::DeviceReset)));
+        assert_eq!(client.state, ClientState::Idle);
+
+        // The reset handler runs before the next command
+        assert_eq!(client.send(&cmd), Err(nb::Error::WouldBlock));
+        assert_eq!(
+            client.tx.s,
+            String::<64>::from("AT+CFUN=4,0\r\nINIT;AT+CFUN=4,0\r\n")
+        );
+
+        // Resets while idle are handled as well
+        p.enqueue(Ok(Vec::new())).unwrap();
+        assert_eq!(client.send(&cmd), Ok(NoResponse));
+        p.enqueue(Err(InternalError::DeviceReset)).unwrap();
+        client.tx.s.clear();
+        assert_eq!(client.send(&cmd), Err(nb::Error::WouldBlock));
+        assert_eq!(client.tx.s, String::<64>::from("INIT;AT+CFUN=4,0\r\n"));
+    }
+
+    #[test]
+    fn wake_after_idle() {
+        let config = Config::new(Mode::NonBlocking)
+            .wake_after_idle(1000)
+            .wake_prefix(b"\r")
+            .wake_delay(50);
+        let (mut client, mut p, _) = setup!(config);
+
+        let cmd = SetModuleFunctionality {
+            fun: Functionality::APM,
+            rst: Some(ResetMode::DontReset),
+        };
+
+        // The link starts out idle
+        assert_eq!(client.send(&cmd), Err(nb::Error::WouldBlock));
+        p.enqueue(Ok(Vec::new())).unwrap();
+        assert_eq!(client.send(&cmd), Ok(NoResponse));
+        assert_eq!(client.tx.s, String::<64>::from("\rAT+CFUN=4,0\r\n"));
+
+        // Recent activity
+        client.tx.s.clear();
+        assert_eq!(client.send(&cmd), Err(nb::Error::WouldBlock));
+        p.enqueue(Ok(Vec::new())).unwrap();
+        assert_eq!(client.send(&cmd), Ok(NoResponse));
+        assert_eq!(client.tx.s, String::<64>::from("AT+CFUN=4,0\r\n"));
+
+        // Idle again
+        client.clock.0 += 1000;
+        client.tx.s.clear();
+        assert_eq!(client.send(&cmd), Err(nb::Error::WouldBlock));
+        assert_eq!(client.tx.s, String::<64>::from("\rAT+CFUN=4,0\r\n"));
+    }
+
+    /// Send `Test2Cmd` after a wakeup to a device ignoring the first
+    /// `dropped` commands, and answering others `delay` ms after echoing
+    /// them. Returns the result, the sent data and the time taken.
+    fn send_after_wakeup(
+        dropped: usize,
+        delay: u64,
+    ) -> (nb::Result<NoResponse, Error>, std::string::String, u64) {
+        use crate::clock::FnClock;
+        use crate::transport::FnTransport;
+        use crate::IngressManager;
+        use core::cell::{Cell, RefCell};
+        use std::boxed::Box;
+
+        // Leaked, as every call needs its own queues
+        let res_q: &'static mut queues::ResQueue<TEST_RX_BUF_LEN> =
+            Box::leak(Box::new(Queue::new()));
+        let urc_q: &'static mut queues::UrcQueue<TEST_RX_BUF_LEN, TEST_URC_CAPACITY> =
+            Box::leak(Box::new(Queue::new()));
+        let com_q: &'static mut queues::ComQueue = Box::leak(Box::new(Queue::new()));
+        let (res_p, res_c) = res_q.split();
+        let (urc_p, urc_c) = urc_q.split();
+        let (com_p, com_c) = com_q.split();
+
+        let ingress = RefCell::new(IngressManager::new(res_p, urc_p, com_c));
+        let ms = Cell::new(0);
+        let answer_at = Cell::new(None);
+        let sent = RefCell::new(std::string::String::new());
+        let commands = Cell::new(0);
+
+        let tx = FnTransport::new(|buf: &[u8]| -> Result<(), ()> {
+            let mut sent = sent.borrow_mut();
+            sent.push_str(core::str::from_utf8(buf).unwrap());
+            if sent.ends_with("\r\n") {
+                commands.set(commands.get() + 1);
+                if commands.get() > dropped {
+                    let mut ingress = ingress.borrow_mut();
+                    ingress.write(b"AT+FUN=1,6\r");
+                    ingress.digest();
+                    answer_at.set(Some(ms.get() + delay));
+                }
+            }
+            Ok(())
+        });
+
+        let clock = FnClock::new(|| {
+            ms.set(ms.get() + 10);
+            let mut ingress = ingress.borrow_mut();
+            if matches!(answer_at.get(), Some(at) if ms.get() >= at) {
+                answer_at.set(None);
+                ingress.write(b"\r\nOK\r\n");
+            }
+            // Timer interrupt, handling commands from the client
+            ingress.digest();
+            Instant::from_millis(ms.get())
+        });
+
+        let config = Config::new(Mode::Timeout)
+            .wake_after_idle(1000)
+            .wake_prefix(b"\r");
+        let mut client: Client<_, _, TEST_RX_BUF_LEN, TEST_URC_CAPACITY> =
+            Client::new(tx, res_c, urc_c, com_p, clock, config);
+        let result = client.send(&Test2Cmd {
+            fun: Functionality::DM,
+            rst: Some(ResetMode::Reset),
+        });
+
+        (result, sent.into_inner(), ms.get())
+    }
+
+    #[test]
+    fn wake_retry() {
+        // Command dropped while waking up, retried after the wake timeout
+        let (result, sent, ms) = send_after_wakeup(1, 100);
+        assert_eq!(result, Ok(NoResponse));
+        assert_eq!(sent, "\rAT+FUN=1,6\r\n\rAT+FUN=1,6\r\n");
+        assert!(ms < 2000);
+
+        // Slow command, echoed before the wake timeout, is never sent twice
+        let (result, sent, ms) = send_after_wakeup(0, 5000);
+        assert_eq!(result, Ok(NoResponse));
+        assert_eq!(sent, "\rAT+FUN=1,6\r\n");
+        assert!(ms >= 5000);
+
+        // Only a single retry, waiting for the full 180 s timeout
+        let (result, sent, ms) = send_after_wakeup(2, 100);
+        assert_eq!(result, Err(nb::Error::Other(Error::Timeout)));
+        assert_eq!(sent, "\rAT+FUN=1,6\r\n\rAT+FUN=1,6\r\n");
+        assert!(ms >= 180_000);
+    }
+
+    #[test]
+    fn urc_does_not_extend_timeout() {
+        let (mut client, _, mut urc_p) = setup!(Config::new(Mode::NonBlocking));
+
+        let cmd = Test2Cmd {
+            fun: Functionality::DM,
+            rst: Some(ResetMode::Reset),
+        };
+        assert_eq!(client.send(&cmd), Err(nb::Error::WouldBlock));
+        let deadline = client.deadline;
+        assert!(deadline.is_some());
+
+        urc_p
+            .enqueue(Vec::<u8, TEST_RX_BUF_LEN>::from_slice(b"RING").unwrap())
+            .unwrap();
+        client.peek_urc_with::<Urc, _>(|_| true);
+
+        assert_eq!(client.deadline, deadline);
+        assert!(client.last_receive.is_some());
+    }
+
     #[test]
     fn blocking() {
         let (mut client, mut p, _) = setup!(Config::new(Mode::Blocking));
@@ -621,6 +1339,87 @@ mod test {
         assert_eq!(client.state, ClientState::Idle);
     }
 
+    #[test]
+    fn tuple_struct() {
+        let (mut client, mut p, _) = setup!(Config::new(Mode::Blocking));
+
+        let cmd = TestUnnamedStruct(Functionality::APM, Some(ResetMode::DontReset));
+
+        let response =
+            Vec::<u8, TEST_RX_BUF_LEN>::from_slice(b"+CUN: 22,16,\"0123456789012345\"").unwrap();
+        p.enqueue(Ok(response)).unwrap();
+
+        assert_eq!(
+            client.send(&cmd),
+            Ok(TestUnnamedResponse(
+                22,
+                16,
+                String::<64>::from("0123456789012345")
+            ))
+        );
+        assert_eq!(client.tx.s, String::<32>::from("AT+CUN=4,0\r\n"));
+        assert_eq!(client.state, ClientState::Idle);
+    }
+
+    #[test]
+    fn tuple_response() {
+        let (mut client, mut p, _) = setup!(Config::new(Mode::Blocking));
+
+        let cmd = TestTupleRespCmd(Functionality::Full);
+
+        let response = Vec::<u8, TEST_RX_BUF_LEN>::from_slice(b"+CUN: 3,\"hello\"").unwrap();
+        p.enqueue(Ok(response)).unwrap();
+
+        assert_eq!(client.send(&cmd), Ok((3, String::<16>::from("hello"))));
+        assert_eq!(client.tx.s, String::<32>::from("AT+CUN=1\r\n"));
+        assert_eq!(client.state, ClientState::Idle);
+    }
+
+    #[test]
+    fn test_command() {
+        let (mut client, mut p, _) = setup!(Config::new(Mode::Blocking));
+
+        let response = Vec::<u8, TEST_RX_BUF_LEN>::from_slice(b"+CFUN: (0,1,4-6),(0-1)").unwrap();
+        p.enqueue(Ok(response)).unwrap();
+
+        assert_eq!(
+            client.send(&SetModuleFunctionality::test()),
+            Ok(ModuleFunctionalityRanges {
+                fun: RangeList(Vec::from_slice(&[0..=0, 1..=1, 4..=6]).unwrap()),
+                rst: RangeList(Vec::from_slice(&[0..=1]).unwrap()),
+            })
+        );
+        assert_eq!(client.tx.s, String::<32>::from("AT+CFUN=?\r\n"));
+        assert_eq!(client.state, ClientState::Idle);
+
+        assert_eq!(SetModuleFunctionalityTest::MAX_TIMEOUT_MS, 180_000);
+    }
+
+    #[test]
+    fn read_command() {
+        let (mut client, mut p, _) = setup!(Config::new(Mode::Blocking));
+
+        let response = Vec::<u8, TEST_RX_BUF_LEN>::from_slice(b"+CFUN: 4").unwrap();
+        p.enqueue(Ok(response)).unwrap();
+
+        assert_eq!(
+            client.send(&SetModuleFunctionality::read()),
+            Ok(ModuleFunctionality {
+                fun: Functionality::APM
+            })
+        );
+        assert_eq!(client.tx.s, String::<32>::from("AT+CFUN?\r\n"));
+        assert_eq!(client.state, ClientState::Idle);
+
+        assert_eq!(
+            SetModuleFunctionalityRead.as_bytes(),
+            SetModuleFunctionality::read().as_bytes()
+        );
+
+        // Read commands share the options of the command
+        assert_eq!(SetModuleFunctionalityRead::MAX_TIMEOUT_MS, 180_000);
+    }
+
     #[test]
     fn urc() {
         let (mut client, _, mut urc_p) = setup!(Config::new(Mode::NonBlocking));
@@ -633,6 +1432,136 @@ mod test {
         assert_eq!(client.state, ClientState::Idle);
     }
 
+    #[test]
+    fn urc_fields() {
+        assert!(matches!(
+            Urc::parse(b"+UUSORD: 3,16"),
+            Some(Urc::SocketDataAvailable {
+                socket: 3,
+                length: 16
+            })
+        ));
+        assert!(matches!(
+            Urc::parse(b"+CREG: 5,\"4E2A\""),
+            Some(Urc::NetworkRegistration(5, Some(ref lac))) if lac == "4E2A"
+        ));
+        assert!(matches!(
+            Urc::parse(b"+CREG: 2"),
+            Some(Urc::NetworkRegistration(2, None))
+        ));
+        assert!(Urc::parse(b"+UUSORD: 3").is_none());
+    }
+
+    #[test]
+    fn urc_without_params() {
+        assert!(matches!(Urc::parse(b"RING"), Some(Urc::Ring)));
+        assert!(matches!(Urc::parse(b"RING\r\n"), Some(Urc::Ring)));
+        assert!(matches!(Urc::parse(b"^SYSSTART"), Some(Urc::SysStart)));
+        assert!(Urc::parse(b"RINGING").is_none());
+    }
+
+    #[test]
+    fn urc_matcher() {
+        let mut matcher = Urc::matcher();
+
+        let mut buf =
+            Vec::<u8, TEST_RX_BUF_LEN>::from_slice(b"+USORF: 0,\"1.2.3.4\",5000,4,\"a\r\n")
+                .unwrap();
+        assert!(matches!(
+            matcher.process(&mut buf),
+            UrcMatcherResult::Incomplete
+        ));
+
+        buf.extend_from_slice(b"b\"\r\nOK\r\n").unwrap();
+        match matcher.process(&mut buf) {
+            UrcMatcherResult::Complete(urc) => assert!(matches!(
+                Urc::parse(&urc),
+                Some(Urc::SocketDataRead(0, ref ip, 5000, 4, ref data)) if ip == "1.2.3.4" && data == "a\r\nb"
+            )),
+            _ => panic!("expected complete URC"),
+        }
+        assert_eq!(&buf[..], b"OK\r\n");
+
+        let mut buf = Vec::<u8, TEST_RX_BUF_LEN>::from_slice(b"+QIURC: \"recv\",1\r\n").unwrap();
+        assert!(matches!(
+            matcher.process(&mut buf),
+            UrcMatcherResult::Incomplete
+        ));
+        buf.extend_from_slice(b"data\r\n").unwrap();
+        match matcher.process(&mut buf) {
+            UrcMatcherResult::Complete(urc) => {
+                assert!(matches!(Urc::parse(&urc), Some(Urc::ReceivedData)))
+            }
+            _ => panic!("expected complete URC"),
+        }
+        assert!(buf.is_empty());
+
+        let mut buf = Vec::<u8, TEST_RX_BUF_LEN>::from_slice(b"+USORFX: 1\r\n").unwrap();
+        assert!(matches!(
+            matcher.process(&mut buf),
+            UrcMatcherResult::NotHandled
+        ));
+        let mut buf = Vec::<u8, TEST_RX_BUF_LEN>::from_slice(b"+CREG: 1\r\n").unwrap();
+        assert!(matches!(
+            matcher.process(&mut buf),
+            UrcMatcherResult::NotHandled
+        ));
+    }
+
+    #[derive(Clone, AtatUrc)]
+    pub enum LineUrc {
+        #[at_urc("RING")]
+        Ring,
+    }
+
+    #[test]
+    fn urc_matcher_without_custom_urcs() {
+        let mut matcher = LineUrc::matcher();
+        let mut buf = Vec::<u8, TEST_RX_BUF_LEN>::from_slice(b"RING\r\n").unwrap();
+        assert!(matches!(
+            matcher.process(&mut buf),
+            UrcMatcherResult::NotHandled
+        ));
+        assert_eq!(&buf[..], b"RING\r\n");
+    }
+
+    #[allow(dead_code)]
+    #[derive(Clone, AtatResp)]
+    #[at_resp(max_len)]
+    pub struct LargeResponse {
+        pub text: String<512>,
+    }
+
+    #[derive(Clone, AtatCmd)]
+    #[at_cmd("+LARGE", LargeResponse)]
+    pub struct LargeCmd;
+
+    #[test]
+    #[should_panic(expected = "does not fit in ingress buffer")]
+    fn response_too_large() {
+        let (mut client, _, _) = setup!(Config::new(Mode::Blocking));
+        let _ = client.send(&LargeCmd);
+    }
+
+    #[allow(dead_code)]
+    #[derive(Clone, AtatResp)]
+    #[at_resp(max_len = 240)]
+    pub struct EdgeResponse {
+        pub text: String<240>,
+    }
+
+    #[derive(Clone, AtatCmd)]
+    #[at_cmd("+EDGE", EdgeResponse)]
+    pub struct EdgeCmd;
+
+    #[test]
+    #[should_panic(expected = "does not fit in ingress buffer")]
+    fn response_prefix_too_large() {
+        // The parameters fit, but not with `\r\n+EDGE: ` and `\r\n\r\nOK\r\n`
+        let (mut client, _, _) = setup!(Config::new(Mode::Blocking));
+        let _ = client.send(&EdgeCmd);
+    }
+
     #[test]
     fn invalid_response() {
         let (mut client, mut p, _) = setup!(Config::new(Mode::Blocking));
@@ -650,4 +1579,122 @@ mod test {
         assert_eq!(client.send(&cmd), Err(nb::Error::Other(Error::Parse)));
         assert_eq!(client.state, ClientState::Idle);
     }
+
+    /// `AT+CMGS` style command, with a Ctrl-Z terminated payload
+    struct SendMessage<'a> {
+        text: &'a str,
+    }
+
+    impl AtatCmd<16> for SendMessage<'_> {
+        type Response = String<16>;
+        type Error = GenericError;
+
+        fn as_bytes(&self) -> Vec<u8, 16> {
+            Vec::from_slice(b"AT+CMGS=5\r\n").unwrap()
+        }
+
+        fn parse(
+            &self,
+            resp: Result<&[u8], &InternalError>,
+        ) -> Result<Self::Response, Error<Self::Error>> {
+            let resp =
+                core::str::from_utf8(resp.map_err(Error::from)?).map_err(|_| Error::Parse)?;
+            Ok(String::from(resp))
+        }
+    }
+
+    impl AtatDataCmd<16> for SendMessage<'_> {
+        const PAYLOAD_TERMINATOR: Option<u8> = Some(0x1A);
+
+        fn payload(&self) -> &[u8] {
+            self.text.as_bytes()
+        }
+    }
+
+    #[test]
+    fn data_phase() {
+        let (mut client, mut p, _) = setup!(Config::new(Mode::NonBlocking));
+
+        let cmd = SendMessage { text: "hello" };
+        assert_eq!(client.send_with_data(&cmd), Err(nb::Error::WouldBlock));
+        assert_eq!(client.state, ClientState::AwaitingPrompt);
+        assert_eq!(client.tx.s, String::<64>::from("AT+CMGS=5\r\n"));
+
+        // Prompt
+        p.enqueue(Err(InternalError::Prompt)).unwrap();
+        assert_eq!(client.send_with_data(&cmd), Err(nb::Error::WouldBlock));
+        assert_eq!(client.state, ClientState::AwaitingResponse);
+        assert_eq!(client.tx.s, String::<64>::from("AT+CMGS=5\r\nhello\u{1a}"));
+
+        p.enqueue(Ok(Vec::from_slice(b"+CMGS: 12").unwrap()))
+            .unwrap();
+        assert_eq!(
+            client.send_with_data(&cmd),
+            Ok(String::<16>::from("+CMGS: 12"))
+        );
+        assert_eq!(client.state, ClientState::Idle);
+    }
+
+    #[test]
+    fn data_phase_write_error() {
+        let (mut client, mut p, _) = setup!(Config::new(Mode::NonBlocking));
+
+        let cmd = SendMessage { text: "hello" };
+        assert_eq!(client.send_with_data(&cmd), Err(nb::Error::WouldBlock));
+
+        // Leave no room for the payload
+        while client.tx.s.push('x').is_ok() {}
+        p.enqueue(Err(InternalError::Prompt)).unwrap();
+        assert_eq!(
+            client.send_with_data(&cmd),
+            Err(nb::Error::Other(Error::Write))
+        );
+        assert_eq!(client.state, ClientState::Idle);
+    }
+
+    #[test]
+    fn data_phase_without_prompt() {
+        let (mut client, mut p, _) = setup!(Config::new(Mode::Timeout));
+
+        let cmd = SendMessage { text: "hello" };
+
+        // Error response instead of the prompt
+        p.enqueue(Err(InternalError::Error(Vec::new()))).unwrap();
+        assert_eq!(
+            client.send_with_data(&cmd),
+            Err(nb::Error::Other(Error::Error(GenericError)))
+        );
+        assert_eq!(client.state, ClientState::Idle);
+        assert_eq!(client.tx.s, String::<64>::from("AT+CMGS=5\r\n"));
+
+        // Final response instead of the prompt
+        client.tx.s.clear();
+        p.enqueue(Ok(Vec::from_slice(b"+CMGS: 12").unwrap()))
+            .unwrap();
+        assert_eq!(
+            client.send_with_data(&cmd),
+            Err(nb::Error::Other(Error::InvalidResponse))
+        );
+        assert_eq!(client.state, ClientState::Idle);
+        assert_eq!(client.tx.s, String::<64>::from("AT+CMGS=5\r\n"));
+
+        // Bare `OK` instead of the prompt
+        client.tx.s.clear();
+        p.enqueue(Ok(Vec::new())).unwrap();
+        assert_eq!(
+            client.send_with_data(&cmd),
+            Err(nb::Error::Other(Error::InvalidResponse))
+        );
+        assert_eq!(client.state, ClientState::Idle);
+        assert_eq!(client.tx.s, String::<64>::from("AT+CMGS=5\r\n"));
+
+        // No prompt at all
+        client.tx.s.clear();
+        assert_eq!(
+            client.send_with_data(&cmd),
+            Err(nb::Error::Other(Error::Timeout))
+        );
+        assert_eq!(client.state, ClientState::Idle);
+        assert_eq!(client.tx.s, String::<64>::from("AT+CMGS=5\r\n"));
+    }
 }
@@ -0,0 +1,323 @@
+//! Monotonic time source used by the [`Client`] to track command cooldowns and
+//! timeouts.
+//!
+//! Adapters are provided for:
+//!
+//! - a plain `FnMut() -> Instant` closure, through [`FnClock`]
+//! - `fugit` instants, eg. from an RTIC `Monotonic`, through `FugitClock`
+//!   for 32 bit timers, and `From<fugit::Instant<u64, ..>>` for 64 bit timers
+//!   (feature `fugit`)
+//! - `embedded-time` clocks, through `EmbeddedTimeClock` (feature
+//!   `embedded-time`)
+//! - `std::time`, through `StdClock` (feature `std`)
+//!
+//! Other time sources can be adapted through [`FnClock`], as long as they
+//! return monotonic milliseconds.
+//!
+//! The timeout for partially received data, in between bytes, is tracked by
+//! the ingress manager, see [`IngressManager::with_silence_timeout`].
+//!
+//! [`Client`]: ../struct.Client.html
+//! [`IngressManager::with_silence_timeout`]: ../struct.IngressManager.html#method.with_silence_timeout
+
+/// A point in time, in milliseconds since an arbitrary epoch.
+#[derive(Debug, Copy, Clone, Hash, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Instant(u64);
+
+impl Instant {
+    #[must_use]
+    pub const fn from_millis(ms: u64) -> Self {
+        Self(ms)
+    }
+
+    #[must_use]
+    pub const fn as_millis(self) -> u64 {
+        self.0
+    }
+
+    /// The instant `ms` milliseconds after `self`
+    #[must_use]
+    pub const fn add_millis(self, ms: u32) -> Self {
+        Self(self.0.saturating_add(ms as u64))
+    }
+
+    /// Milliseconds elapsed from `earlier` to `self`, or zero if `earlier` is
+    /// later than `self`
+    #[must_use]
+    pub const fn millis_since(self, earlier: Self) -> u64 {
+        self.0.saturating_sub(earlier.0)
+    }
+}
+
+/// A monotonic clock.
+///
+/// The returned instants must never decrease.
+pub trait Clock {
+    fn now(&mut self) -> Instant;
+}
+
+/// [`Clock`] adapter for a closure returning the current time.
+///
+/// Example:
+/// ```
+/// use atat::clock::{Clock, FnClock, Instant};
+///
+/// let mut ticks = 0;
+/// let mut clock = FnClock::new(|| {
+///     ticks += 10;
+///     Instant::from_millis(ticks)
+/// });
+///
+/// let start = clock.now();
+/// assert_eq!(clock.now().millis_since(start), 10);
+/// ```
+pub struct FnClock<F>(F);
+
+impl<F> FnClock<F>
+where
+    F: FnMut() -> Instant,
+{
+    pub const fn new(f: F) -> Self {
+        Self(f)
+    }
+}
+
+impl<F> Clock for FnClock<F>
+where
+    F: FnMut() -> Instant,
+{
+    fn now(&mut self) -> Instant {
+        (self.0)()
+    }
+}
+
+/// [`Clock`] adapter for a closure returning 32 bit `fugit` instants, eg.
+/// `FugitClock::new(monotonics::now)` for an RTIC `Monotonic`.
+///
+/// The wrapping 32 bit ticks are extended to 64 bits, keeping the clock
+/// monotonic across wraps. This requires `now` to be called at least once per
+/// wrap of the timer, eg. every ~49 days for a 1 kHz timer.
+#[cfg(feature = "fugit")]
+pub struct FugitClock<F, const NOM: u32, const DENOM: u32> {
+    f: F,
+    last: u32,
+    wraps: u64,
+}
+
+#[cfg(feature = "fugit")]
+impl<F, const NOM: u32, const DENOM: u32> FugitClock<F, NOM, DENOM>
+where
+    F: FnMut() -> fugit::Instant<u32, NOM, DENOM>,
+{
+    pub const fn new(f: F) -> Self {
+        Self {
+            f,
+            last: 0,
+            wraps: 0,
+        }
+    }
+}
+
+#[cfg(feature = "fugit")]
+impl<F, const NOM: u32, const DENOM: u32> Clock for FugitClock<F, NOM, DENOM>
+where
+    F: FnMut() -> fugit::Instant<u32, NOM, DENOM>,
+{
+    fn now(&mut self) -> Instant {
+        let ticks = (self.f)().ticks();
+        if ticks < self.last {
+            self.wraps += 1;
+        }
+        self.last = ticks;
+        fugit::Instant::<u64, NOM, DENOM>::from_ticks(self.wraps << 32 | u64::from(ticks)).into()
+    }
+}
+
+#[cfg(feature = "fugit")]
+impl<const NOM: u32, const DENOM: u32> From<fugit::Instant<u64, NOM, DENOM>> for Instant {
+    fn from(instant: fugit::Instant<u64, NOM, DENOM>) -> Self {
+        let ms: fugit::MillisDurationU64 = instant.duration_since_epoch().convert();
+        Self(ms.ticks())
+    }
+}
+
+/// [`Clock`] adapter for an `embedded_time::Clock`, eg. a hardware timer.
+///
+/// Ticks of clocks narrower than 64 bits are extended to 64 bits, keeping the
+/// clock monotonic across wraps, as long as `now` is called at least once per
+/// wrap of the underlying clock. If reading the clock fails, the last instant
+/// read is returned.
+#[cfg(feature = "embedded-time")]
+pub struct EmbeddedTimeClock<C> {
+    clock: C,
+    last: u64,
+    wraps: u64,
+}
+
+#[cfg(feature = "embedded-time")]
+impl<C> EmbeddedTimeClock<C>
+where
+    C: embedded_time::Clock,
+    C::T: Into<u64>,
+{
+    pub const fn new(clock: C) -> Self {
+        Self {
+            clock,
+            last: 0,
+            wraps: 0,
+        }
+    }
+}
+
+#[cfg(feature = "embedded-time")]
+impl<C> Clock for EmbeddedTimeClock<C>
+where
+    C: embedded_time::Clock,
+    C::T: Into<u64>,
+{
+    fn now(&mut self) -> Instant {
+        let ticks = self
+            .clock
+            .try_now()
+            .map_or(self.last, |now| now.duration_since_epoch().integer().into());
+        if ticks < self.last {
+            self.wraps += 1;
+        }
+        self.last = ticks;
+
+        // Ticks span the width of the clock's integer type, eg. `u32`
+        #[allow(clippy::cast_possible_truncation)]
+        let bits = 8 * core::mem::size_of::<C::T>() as u32;
+        let ticks = self.wraps.checked_shl(bits).unwrap_or(0) | ticks;
+
+        let scaling = C::SCALING_FACTOR;
+        let ms = u128::from(ticks) * u128::from(*scaling.numerator()) * 1000
+            / u128::from(*scaling.denominator());
+        #[allow(clippy::cast_possible_truncation)]
+        Instant(ms as u64)
+    }
+}
+
+/// [`Clock`] based on `std::time::Instant`, counting from its creation.
+#[cfg(feature = "std")]
+#[derive(Debug, Clone)]
+pub struct StdClock(std::time::Instant);
+
+#[cfg(feature = "std")]
+impl StdClock {
+    #[must_use]
+    pub fn new() -> Self {
+        Self(std::time::Instant::now())
+    }
+}
+
+#[cfg(feature = "std")]
+impl Default for StdClock {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(feature = "std")]
+impl Clock for StdClock {
+    fn now(&mut self) -> Instant {
+        #[allow(clippy::cast_possible_truncation)]
+        Instant(self.0.elapsed().as_millis() as u64)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn instant_saturates() {
+        let start = Instant::from_millis(1000);
+        assert_eq!(start.add_millis(500).as_millis(), 1500);
+        assert_eq!(
+            Instant::from_millis(u64::MAX).add_millis(1).as_millis(),
+            u64::MAX
+        );
+        assert_eq!(start.add_millis(500).millis_since(start), 500);
+        assert_eq!(start.millis_since(start.add_millis(500)), 0);
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn std_clock() {
+        let mut clock = StdClock::new();
+        let start = clock.now();
+        std::thread::sleep(std::time::Duration::from_millis(20));
+        let now = clock.now();
+        assert!(now.millis_since(start) >= 20);
+        assert!(clock.now() >= now);
+    }
+
+    #[cfg(feature = "fugit")]
+    #[test]
+    fn from_fugit_instant() {
+        let instant: Instant = fugit::TimerInstantU64::<1_000_000>::from_ticks(2_500_999).into();
+        assert_eq!(instant, Instant::from_millis(2500));
+        let instant: Instant = fugit::TimerInstantU64::<32_768>::from_ticks(65_536).into();
+        assert_eq!(instant, Instant::from_millis(2000));
+    }
+
+    #[cfg(feature = "embedded-time")]
+    mod embedded_time_clock {
+        use super::*;
+        use core::cell::Cell;
+        use embedded_time::{clock, fraction::Fraction};
+
+        /// 32 kHz clock reading the next of `ticks` on every call, failing
+        /// once they run out
+        struct TestClock<'a>(Cell<&'a [u32]>);
+
+        impl embedded_time::Clock for TestClock<'_> {
+            type T = u32;
+
+            const SCALING_FACTOR: Fraction = Fraction::new(1, 32_768);
+
+            fn try_now(&self) -> Result<embedded_time::Instant<Self>, clock::Error> {
+                let (ticks, rest) = self.0.get().split_first().ok_or(clock::Error::NotRunning)?;
+                self.0.set(rest);
+                Ok(embedded_time::Instant::new(*ticks))
+            }
+        }
+
+        #[test]
+        fn converts_ticks() {
+            let mut clock = EmbeddedTimeClock::new(TestClock(Cell::new(&[32_768, 49_152])));
+            assert_eq!(clock.now(), Instant::from_millis(1000));
+            assert_eq!(clock.now(), Instant::from_millis(1500));
+        }
+
+        #[test]
+        fn wraps() {
+            let ticks = [u32::MAX - 32_767, 0, 32_768];
+            let mut clock = EmbeddedTimeClock::new(TestClock(Cell::new(&ticks)));
+            let start = clock.now();
+            assert_eq!(clock.now().millis_since(start), 1000);
+            assert_eq!(clock.now().millis_since(start), 2000);
+        }
+
+        #[test]
+        fn error_returns_last_instant() {
+            let mut clock = EmbeddedTimeClock::new(TestClock(Cell::new(&[32_768])));
+            assert_eq!(clock.now(), Instant::from_millis(1000));
+            assert_eq!(clock.now(), Instant::from_millis(1000));
+        }
+    }
+
+    #[cfg(feature = "fugit")]
+    #[test]
+    fn fugit_clock_wraps() {
+        let mut ticks = [u32::MAX - 1, u32::MAX, 1, 3].iter();
+        let mut clock =
+            FugitClock::new(|| fugit::TimerInstantU32::<1000>::from_ticks(*ticks.next().unwrap()));
+
+        let start = clock.now();
+        assert_eq!(clock.now().millis_since(start), 1);
+        assert_eq!(clock.now().millis_since(start), 3);
+        assert_eq!(clock.now().millis_since(start), 5);
+    }
+}
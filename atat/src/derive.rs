@@ -17,7 +17,7 @@ macro_rules! impl_length {
     };
 }
 
-impl_length!(char, 1);
+impl_length!(char, 3);
 impl_length!(bool, 5);
 impl_length!(isize, 19);
 impl_length!(usize, 20);
@@ -35,7 +35,8 @@ impl_length!(f32, 42);
 impl_length!(f64, 312);
 
 impl<const T: usize> AtatLen for String<T> {
-    const LEN: usize = T;
+    // Strings are quoted
+    const LEN: usize = T + 2;
 }
 
 impl<T: AtatLen> AtatLen for Option<T> {
@@ -57,18 +58,82 @@ impl<const N: usize> AtatLen for CharVec<N> {
     const LEN: usize = N;
 }
 
+/// Adapter implementing [`serde_at::Writer`] for a closure. Used by
+/// [`atat_derive`] to stream serialized commands through
+/// [`AtatCmd::write_to`](crate::AtatCmd::write_to).
+///
+/// [`atat_derive`]: https://crates.io/crates/atat_derive
+pub struct FnWriter<F, E> {
+    write: F,
+    error: Option<E>,
+}
+
+impl<F, E> FnWriter<F, E>
+where
+    F: FnMut(&[u8]) -> Result<(), E>,
+{
+    pub fn new(write: F) -> Self {
+        Self { write, error: None }
+    }
+
+    /// Take the error returned by the closure, if any
+    pub fn take_error(&mut self) -> Option<E> {
+        self.error.take()
+    }
+}
+
+impl<F, E> serde_at::Writer for FnWriter<F, E>
+where
+    F: FnMut(&[u8]) -> Result<(), E>,
+{
+    fn write_bytes(&mut self, bytes: &[u8]) -> serde_at::ser::Result<()> {
+        (self.write)(bytes).map_err(|e| {
+            self.error = Some(e);
+            serde_at::ser::Error::Write
+        })
+    }
+}
+
+/// [`serde_at::Writer`] discarding the serialized bytes, failing once more
+/// than `max` bytes are written. Used by [`atat_derive`] to check that a
+/// command fits in `LEN` bytes before streaming any of it.
+///
+/// [`atat_derive`]: https://crates.io/crates/atat_derive
+pub struct LenWriter {
+    len: usize,
+    max: usize,
+}
+
+impl LenWriter {
+    #[must_use]
+    pub const fn new(max: usize) -> Self {
+        Self { len: 0, max }
+    }
+}
+
+impl serde_at::Writer for LenWriter {
+    fn write_bytes(&mut self, bytes: &[u8]) -> serde_at::ser::Result<()> {
+        self.len += bytes.len();
+        if self.len > self.max {
+            return Err(serde_at::ser::Error::BufferFull);
+        }
+        Ok(())
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use crate as atat;
-    use atat::{derive::AtatLen, AtatCmd};
-    use atat_derive::{AtatCmd, AtatEnum, AtatResp};
+    use atat::{derive::AtatLen, AtatCmd, AtatResp};
+    use atat_derive::{AtatCmd, AtatEnum, AtatFlags, AtatResp};
     use heapless::{String, Vec};
-    use serde_at::{from_str, to_string, SerializeOptions};
+    use proptest::{num, prelude::*};
+    use serde_at::{from_str, to_string, CharVec, SerializeOptions};
 
     #[derive(Debug, PartialEq, AtatResp)]
     struct NoResponse {}
 
-    #[derive(Debug, PartialEq, AtatEnum)]
+    #[derive(Debug, Clone, PartialEq, AtatEnum)]
     enum SimpleEnum {
         #[at_arg(default, value = 0)]
         A,
@@ -110,6 +175,96 @@ mod tests {
         SingleSimpleTupleLifetime(#[at_arg(len = 10)] &'a str),
     }
 
+    #[derive(Debug, PartialEq, AtatEnum)]
+    enum PdpType {
+        #[at_arg(value = "IP")]
+        IPv4,
+        #[at_arg(value = "IPV6")]
+        IPv6,
+        #[at_arg(default, value = "IPV4V6")]
+        IPv4v6,
+    }
+
+    #[derive(Debug, PartialEq, AtatEnum)]
+    #[at_enum(quoted = false)]
+    enum PinStatus {
+        #[at_arg(value = "READY")]
+        Ready,
+        #[at_arg(value = "SIM PIN")]
+        SimPin,
+    }
+
+    #[derive(Debug, PartialEq, AtatEnum)]
+    #[at_enum(quoted = false)]
+    enum NetworkMode {
+        #[at_arg(value = "2G")]
+        Gsm,
+        #[at_arg(value = "3GPP")]
+        ThreeGpp,
+    }
+
+    #[derive(Debug, PartialEq, AtatResp)]
+    struct NetworkModeResponse {
+        mode: NetworkMode,
+        fallback: NetworkMode,
+    }
+
+    #[derive(Debug, PartialEq, AtatEnum)]
+    enum RadioAccess {
+        #[at_arg(value = 0)]
+        Gsm,
+        #[at_arg(value = 7)]
+        Lte,
+        #[at_arg(other)]
+        Unknown(u8),
+    }
+
+    #[derive(Debug, PartialEq, AtatEnum)]
+    enum Storage {
+        #[at_arg(value = "SM")]
+        Sim,
+        #[at_arg(value = "ME")]
+        Modem,
+        #[at_arg(other)]
+        Unknown(String<4>),
+    }
+
+    #[derive(Debug, PartialEq, AtatResp)]
+    struct OtherEnumResponse {
+        act: RadioAccess,
+        storage: Storage,
+    }
+
+    #[derive(Debug, PartialEq, Default, AtatFlags)]
+    #[at_enum(u16)]
+    struct ReportingMask {
+        #[at_arg(value = 0x01)]
+        registration: bool,
+        #[at_arg(value = 0x02)]
+        location: bool,
+        #[at_arg(value = 0x100)]
+        cause: bool,
+    }
+
+    #[derive(Debug, PartialEq, AtatFlags)]
+    struct GenericMask<const N: usize> {
+        #[at_arg(value = 0x01)]
+        enabled: bool,
+    }
+
+    #[derive(Debug, PartialEq, AtatCmd)]
+    #[at_cmd("+CEREG", NoResponse)]
+    struct SetReporting {
+        mask: ReportingMask,
+    }
+
+    #[derive(Debug, PartialEq, AtatResp)]
+    #[at_resp(max_len)]
+    struct StringEnumResponse {
+        status: PinStatus,
+        pdp_type: PdpType,
+    }
+
     #[derive(Debug, PartialEq, AtatCmd)]
     #[at_cmd("+CFUN", NoResponse)]
     struct LengthTester<'a> {
@@ -128,7 +283,7 @@ mod tests {
 
     #[test]
     fn test_atat_len() {
-        assert_eq!(<char as AtatLen>::LEN, 1);
+        assert_eq!(<char as AtatLen>::LEN, 3);
         assert_eq!(<bool as AtatLen>::LEN, 5);
         assert_eq!(<isize as AtatLen>::LEN, 19);
         assert_eq!(<usize as AtatLen>::LEN, 20);
@@ -148,12 +303,16 @@ mod tests {
         assert_eq!(<SimpleEnum as AtatLen>::LEN, 3);
         assert_eq!(<SimpleEnumU32 as AtatLen>::LEN, 10);
         // (fields) + (n_fields - 1)
-        // (3 + 128 + 2 + 150 + 3 + 10 + 3 + (10*5)) + 7
+        // (3 + 130 + 2 + 150 + 3 + 10 + 3 + (10*5)) + 7
         assert_eq!(
             <LengthTester<'_> as AtatLen>::LEN,
-            (3 + 128 + 2 + 150 + 3 + 10 + 3) + 6
+            (3 + 130 + 2 + 150 + 3 + 10 + 3) + 6
         );
-        assert_eq!(<MixedEnum<'_> as AtatLen>::LEN, (3 + 3 + 10 + 20 + 10) + 4);
+        assert_eq!(<MixedEnum<'_> as AtatLen>::LEN, (3 + 3 + 12 + 20 + 10) + 4);
+
+        // `SIM PIN` unquoted + `"IPV4V6"` quoted + separator
+        assert_eq!(<StringEnumResponse as AtatResp>::MAX_LEN, Some(7 + 8 + 1));
+        assert_eq!(<NoResponse as AtatResp>::MAX_LEN, None);
     }
 
     #[test]
@@ -174,6 +333,137 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_string_enum() {
+        assert_eq!(<PdpType as AtatLen>::LEN, 8);
+        assert_eq!(<PinStatus as AtatLen>::LEN, 7);
+        assert_eq!(PdpType::default(), PdpType::IPv4v6);
+
+        assert_eq!(
+            to_string::<_, 8, 3>(
+                &PdpType::IPv6,
+                String::from("CMD"),
+                SerializeOptions::default()
+            )
+            .unwrap(),
+            String::<8>::from("\"IPV6\"")
+        );
+        assert_eq!(
+            to_string::<_, 8, 3>(
+                &PinStatus::SimPin,
+                String::from("CMD"),
+                SerializeOptions::default()
+            )
+            .unwrap(),
+            String::<8>::from("SIM PIN")
+        );
+
+        assert_eq!(
+            from_str::<StringEnumResponse>("+CPIN: READY,\"IPV4V6\""),
+            Ok(StringEnumResponse {
+                status: PinStatus::Ready,
+                pdp_type: PdpType::IPv4v6
+            })
+        );
+        assert_eq!(
+            from_str::<StringEnumResponse>("+CPIN: SIM PIN,\"IP\"\r\n"),
+            Ok(StringEnumResponse {
+                status: PinStatus::SimPin,
+                pdp_type: PdpType::IPv4
+            })
+        );
+        assert!(from_str::<PdpType>("\"PPP\"").is_err());
+
+        // Unquoted values starting with a digit
+        assert_eq!(
+            to_string::<_, 8, 3>(
+                &NetworkMode::ThreeGpp,
+                String::from("CMD"),
+                SerializeOptions::default()
+            )
+            .unwrap(),
+            String::<8>::from("3GPP")
+        );
+        assert_eq!(
+            from_str::<NetworkModeResponse>("+UMODE: 3GPP,2G\r\n"),
+            Ok(NetworkModeResponse {
+                mode: NetworkMode::ThreeGpp,
+                fallback: NetworkMode::Gsm
+            })
+        );
+    }
+
+    #[test]
+    fn test_other_enum() {
+        assert_eq!(
+            from_str::<OtherEnumResponse>("+COPS: 7,\"SM\""),
+            Ok(OtherEnumResponse {
+                act: RadioAccess::Lte,
+                storage: Storage::Sim
+            })
+        );
+        assert_eq!(
+            from_str::<OtherEnumResponse>("+COPS: 9,\"MT\""),
+            Ok(OtherEnumResponse {
+                act: RadioAccess::Unknown(9),
+                storage: Storage::Unknown(String::from("MT"))
+            })
+        );
+        // Unknown values must still fit the `other` variant
+        assert!(from_str::<RadioAccess>("300").is_err());
+        assert!(from_str::<Storage>("\"TOOLONG\"").is_err());
+
+        assert_eq!(
+            to_string::<_, 8, 3>(
+                &RadioAccess::Unknown(9),
+                String::from("CMD"),
+                SerializeOptions::default()
+            )
+            .unwrap(),
+            String::<8>::from("9")
+        );
+        assert_eq!(
+            to_string::<_, 8, 3>(
+                &Storage::Unknown(String::from("MT")),
+                String::from("CMD"),
+                SerializeOptions::default()
+            )
+            .unwrap(),
+            String::<8>::from("\"MT\"")
+        );
+        assert_eq!(<Storage as AtatLen>::LEN, 6);
+    }
+
+    #[test]
+    fn test_flags() {
+        let mask = ReportingMask {
+            registration: true,
+            location: false,
+            cause: true,
+        };
+        assert_eq!(mask.bits(), 0x101);
+        assert_eq!(ReportingMask::from_bits(0x101), mask);
+        // Unknown bits are ignored
+        assert_eq!(ReportingMask::from_bits(0x1101), mask);
+        assert_eq!(<ReportingMask as AtatLen>::LEN, 5);
+
+        assert_eq!(
+            SetReporting { mask }.as_bytes(),
+            Vec::<u8, 32>::from_slice(b"AT+CEREG=257\r\n").unwrap()
+        );
+        assert_eq!(
+            from_str::<ReportingMask>("2"),
+            Ok(ReportingMask {
+                location: true,
+                ..ReportingMask::default()
+            })
+        );
+        assert_eq!(
+            from_str::<GenericMask<1>>("1"),
+            Ok(GenericMask { enabled: true })
+        );
+    }
+
     #[test]
     fn test_mixed_enum() {
         assert_eq!(
@@ -248,4 +538,129 @@ mod tests {
             from_str::<MixedEnum<'_>>("6,\"abc\"")
         );
     }
+
+    #[derive(Debug, Clone, PartialEq, AtatEnum)]
+    enum Nested {
+        #[at_arg(value = 0)]
+        Off,
+        #[at_arg(value = 1)]
+        Level(u8),
+        #[at_arg(value = 2)]
+        Named(u16, String<8>),
+    }
+
+    /// Command deserializing back from its own parameters
+    #[derive(Debug, Clone, PartialEq, AtatCmd, AtatResp)]
+    #[at_cmd("+RT", RoundTrip)]
+    struct RoundTrip {
+        a: u8,
+        b: i32,
+        c: u64,
+        d: bool,
+        e: f32,
+        f: f64,
+        g: char,
+        h: String<16>,
+        i: CharVec<8>,
+        j: SimpleEnum,
+        k: Nested,
+        l: Option<i16>,
+        m: Option<String<4>>,
+    }
+
+    /// Quoted strings can hold anything but quotes
+    const STRING: &str = "[ !#-~]*";
+    /// Unquoted values end at separators, and skip whitespace
+    const CHARS: &str = "[0-9A-Za-z_.-]*";
+
+    fn string<const N: usize>() -> impl Strategy<Value = String<N>> {
+        proptest::string::string_regex(STRING)
+            .unwrap()
+            .prop_map(|s| s.chars().take(N).collect())
+    }
+
+    fn char_vec<const N: usize>() -> impl Strategy<Value = CharVec<N>> {
+        proptest::string::string_regex(CHARS)
+            .unwrap()
+            .prop_map(|s| CharVec(s.chars().take(N).collect()))
+    }
+
+    fn simple_enum() -> impl Strategy<Value = SimpleEnum> {
+        prop_oneof![
+            Just(SimpleEnum::A),
+            Just(SimpleEnum::B),
+            Just(SimpleEnum::C),
+            Just(SimpleEnum::D)
+        ]
+    }
+
+    fn nested() -> impl Strategy<Value = Nested> {
+        prop_oneof![
+            Just(Nested::Off),
+            any::<u8>().prop_map(Nested::Level),
+            (any::<u16>(), string()).prop_map(|(n, s)| Nested::Named(n, s)),
+        ]
+    }
+
+    /// Optional parameters can only be omitted at the end of a command
+    fn trailing_options() -> impl Strategy<Value = (Option<i16>, Option<String<4>>)> {
+        prop_oneof![
+            Just((None, None)),
+            any::<i16>().prop_map(|l| (Some(l), None)),
+            (any::<i16>(), string()).prop_map(|(l, m)| (Some(l), Some(m))),
+        ]
+    }
+
+    prop_compose! {
+        fn round_trip()(
+            (a, b, c, d) in (any::<u8>(), any::<i32>(), any::<u64>(), any::<bool>()),
+            e in num::f32::NORMAL | num::f32::SUBNORMAL | num::f32::ZERO,
+            f in num::f64::NORMAL | num::f64::SUBNORMAL | num::f64::ZERO,
+            g in proptest::char::range(' ', '~'),
+            h in string(),
+            i in char_vec(),
+            j in simple_enum(),
+            k in nested(),
+            (l, m) in trailing_options(),
+        ) -> RoundTrip {
+            RoundTrip { a, b, c, d, e, f, g, h, i, j, k, l, m }
+        }
+    }
+
+    /// Length of `value` serialized on its own
+    fn serialized_len<T: serde_at::serde::Serialize>(value: &T) -> usize {
+        serde_at::to_vec::<_, 512, 3>(value, String::from("CMD"), SerializeOptions::default())
+            .unwrap()
+            .len()
+    }
+
+    proptest! {
+        #[test]
+        fn prop_round_trip(cmd in round_trip()) {
+            // Fails if `AtatLen` underestimates the command length
+            let bytes = cmd.try_as_bytes().unwrap();
+
+            let params = bytes
+                .strip_prefix(b"AT+RT=")
+                .and_then(|b| b.strip_suffix(b"\r\n"))
+                .unwrap();
+            prop_assert_eq!(serde_at::from_slice::<RoundTrip>(params), Ok(cmd));
+        }
+
+        #[test]
+        fn prop_atat_len(cmd in round_trip()) {
+            prop_assert!(serialized_len(&cmd.a) <= u8::LEN);
+            prop_assert!(serialized_len(&cmd.b) <= i32::LEN);
+            prop_assert!(serialized_len(&cmd.c) <= u64::LEN);
+            prop_assert!(serialized_len(&cmd.d) <= bool::LEN);
+            prop_assert!(serialized_len(&cmd.e) <= f32::LEN);
+            prop_assert!(serialized_len(&cmd.f) <= f64::LEN);
+            prop_assert!(serialized_len(&cmd.g) <= char::LEN);
+            prop_assert!(serialized_len(&cmd.h) <= String::<16>::LEN);
+            prop_assert!(serialized_len(&cmd.i) <= CharVec::<8>::LEN);
+            prop_assert!(serialized_len(&cmd.j) <= SimpleEnum::LEN);
+            prop_assert!(serialized_len(&cmd.k) <= Nested::LEN);
+            prop_assert!(serialized_len(&cmd.m) <= Option::<String<4>>::LEN);
+        }
+    }
 }
@@ -2,7 +2,7 @@ use crate::{
     atat_log,
     helpers::{get_line, LossyStr, SliceExt},
     urc_matcher::{UrcMatcher, UrcMatcherResult},
-    InternalError,
+    InternalError, MAX_ERROR_LEN,
 };
 use heapless::Vec;
 
@@ -17,6 +17,15 @@ pub trait Digester {
 
     fn force_receive_state(&mut self);
 
+    /// Whether the digester is in the middle of receiving a response to a
+    /// command. Used by the [`IngressManager`] to notify the client when a
+    /// partial response is flushed on a silence timeout.
+    ///
+    /// [`IngressManager`]: struct.IngressManager.html
+    fn awaiting_response(&self) -> bool {
+        false
+    }
+
     fn digest<const L: usize>(
         &mut self,
         buf: &mut Vec<u8, L>,
@@ -28,6 +37,11 @@ pub trait Digester {
 pub enum DigestResult<const L: usize> {
     Urc(Vec<u8, L>),
     Response(Result<Vec<u8, L>, InternalError>),
+    /// A prompt for the payload of a command with a data phase, eg. `>`
+    Prompt,
+    /// The echo of a command, eg. `AT+CGMR\r\r\n`, after which a response
+    /// is awaited. Reported to observers as `Event::EchoReceived`.
+    Echo(Vec<u8, L>),
     None,
 }
 
@@ -46,6 +60,10 @@ impl Default for State {
     }
 }
 
+/// First characters of lines treated as URCs, covering standard URCs, eg.
+/// `+CREG: 1`, and vendor specific URCs, eg. `^SYSSTART` or `%CESQ: 1`
+const URC_PREFIXES: &[u8] = b"+^%#";
+
 /// A Digester that tries to implement the basic AT standard.
 /// This digester should work for most usecases of ATAT.
 ///
@@ -58,6 +76,42 @@ pub struct DefaultDigester {
     /// A flag that is set to `true` when the buffer is cleared
     /// with an incomplete response.
     buf_incomplete: bool,
+
+    /// Known unsolicited words without a URC prefix, eg. `RING`
+    urc_words: &'static [&'static [u8]],
+}
+
+impl DefaultDigester {
+    /// Treat lines starting with any of `words` as URCs, in addition to lines
+    /// starting with `+`, `^`, `%` or `#`. This allows handling bare word
+    /// URCs like `RING`, `NO CARRIER` or `SMS DONE`.
+    #[must_use]
+    pub const fn with_urc_words(mut self, words: &'static [&'static [u8]]) -> Self {
+        self.urc_words = words;
+        self
+    }
+
+    fn is_urc(&self, buf: &[u8]) -> bool {
+        matches!(buf.first(), Some(c) if URC_PREFIXES.contains(c))
+            || self.urc_words.iter().any(|w| {
+                // The word must be followed by the end of the line or its
+                // parameters, so eg. `RINGING` is not taken as `RING`
+                buf.starts_with(w)
+                    && matches!(buf.get(w.len()), Some(&c) if c == Self::LINE_TERM_CHAR
+                        || c == Self::FORMAT_CHAR
+                        || c == b':')
+            })
+    }
+
+    /// Whether `buf` could be the start of a known unsolicited word, still
+    /// waiting for the end of the word
+    fn is_partial_urc_word(&self, buf: &[u8]) -> bool {
+        !buf.is_empty()
+            && self
+                .urc_words
+                .iter()
+                .any(|w| w.len() >= buf.len() && w.starts_with(buf))
+    }
 }
 
 impl Digester for DefaultDigester {
@@ -70,6 +124,10 @@ impl Digester for DefaultDigester {
         self.state = State::ReceivingResponse;
     }
 
+    fn awaiting_response(&self) -> bool {
+        self.state == State::ReceivingResponse
+    }
+
     #[allow(clippy::cognitive_complexity)]
     fn digest<const L: usize>(
         &mut self,
@@ -95,7 +153,7 @@ impl Digester for DefaultDigester {
             State::Idle => {
                 // Handle AT echo responses
                 if !self.buf_incomplete && buf.get(0..2) == Some(b"AT") {
-                    if get_line::<L, L>(
+                    if let Some(echo) = get_line::<L, L>(
                         buf,
                         &[Self::LINE_TERM_CHAR],
                         Self::LINE_TERM_CHAR,
@@ -103,16 +161,15 @@ impl Digester for DefaultDigester {
                         false,
                         false,
                         false,
-                    )
-                    .is_some()
-                    {
+                    ) {
                         self.state = State::ReceivingResponse;
                         self.buf_incomplete = false;
                         atat_log!(trace, "Switching to state ReceivingResponse");
+                        return DigestResult::Echo(echo);
                     }
 
                 // Handle URCs
-                } else if !self.buf_incomplete && buf.get(0) == Some(&b'+') {
+                } else if !self.buf_incomplete && self.is_urc(buf) {
                     // Try to apply the custom URC matcher
                     let handled = match urc_matcher.process(buf) {
                         UrcMatcherResult::NotHandled => false,
@@ -137,10 +194,12 @@ impl Digester for DefaultDigester {
                             return DigestResult::Urc(line);
                         }
                     }
-                // Text sent by the device that is not a valid response type (e.g. starting
-                // with "AT" or "+") can be ignored. Clear the buffer, but only if we can
-                // ensure that we don't accidentally break a valid response.
+                } else if !self.buf_incomplete && self.is_partial_urc_word(buf) {
+                    // Wait for the rest of a possible unsolicited word
                 } else if self.buf_incomplete || buf.len() > 2 {
+                    // Text sent by the device that is not a valid response type (e.g. starting
+                    // with "AT" or "+") can be ignored. Clear the buffer, but only if we can
+                    // ensure that we don't accidentally break a valid response.
                     atat_log!(
                         error,
                         "Clearing buffer with invalid response (incomplete: {}, buflen: {})",
@@ -217,7 +276,8 @@ impl Digester for DefaultDigester {
                             true,
                             true,
                         )
-                        .unwrap_or_else(|| Vec::from_slice(&line).unwrap_or_default()),
+                        // Truncate, rather than drop, overly long error messages
+                        .unwrap_or_else(|| line.iter().take(MAX_ERROR_LEN).copied().collect()),
                     ))
                 } else if get_line::<L, L>(
                     buf,
@@ -240,7 +300,9 @@ impl Digester for DefaultDigester {
                     )
                     .is_some()
                 {
-                    Ok(Vec::new())
+                    atat_log!(trace, "Switching to state Idle");
+                    self.state = State::Idle;
+                    return DigestResult::Prompt;
                 } else {
                     return DigestResult::None;
                 };
@@ -276,7 +338,7 @@ mod test {
         buf.extend_from_slice(b"AT\r\r\n\r\n").unwrap();
         assert_eq!(
             digester.digest(&mut buf, &mut urc_matcher),
-            DigestResult::None
+            DigestResult::Echo(Vec::from_slice(b"AT\r\r\n\r\n").unwrap())
         );
 
         assert_eq!(digester.state, State::ReceivingResponse);
@@ -299,7 +361,7 @@ mod test {
         buf.extend_from_slice(b"AT+USORD=3,16\r\n").unwrap();
         assert_eq!(
             digester.digest(&mut buf, &mut urc_matcher),
-            DigestResult::None
+            DigestResult::Echo(Vec::from_slice(b"AT+USORD=3,16\r\n").unwrap())
         );
         assert_eq!(digester.state, State::ReceivingResponse);
 
@@ -346,7 +408,7 @@ mod test {
         buf.extend_from_slice(b"AT+GMR\r\r\n").unwrap();
         assert_eq!(
             digester.digest(&mut buf, &mut urc_matcher),
-            DigestResult::None
+            DigestResult::Echo(Vec::from_slice(b"AT+GMR\r\r\n").unwrap())
         );
         assert_eq!(digester.state, State::ReceivingResponse);
 
@@ -382,6 +444,83 @@ mod test {
         }
     }
 
+    #[test]
+    fn vendor_urc() {
+        let mut digester = DefaultDigester::default();
+        let mut urc_matcher = DefaultUrcMatcher::default();
+        let mut buf = Vec::<u8, TEST_RX_BUF_LEN>::new();
+
+        buf.extend_from_slice(b"^SYSSTART\r\n%CESQ: 1,2\r\n")
+            .unwrap();
+        assert_eq!(
+            digester.digest(&mut buf, &mut urc_matcher),
+            DigestResult::Urc(Vec::from_slice(b"^SYSSTART").unwrap())
+        );
+        assert_eq!(
+            digester.digest(&mut buf, &mut urc_matcher),
+            DigestResult::Urc(Vec::from_slice(b"%CESQ: 1,2").unwrap())
+        );
+        assert_eq!(buf, Vec::<_, TEST_RX_BUF_LEN>::new());
+        assert_eq!(digester.state, State::Idle);
+    }
+
+    #[test]
+    fn urc_words() {
+        let mut digester = DefaultDigester::default().with_urc_words(&[b"RING", b"NO CARRIER"]);
+        let mut urc_matcher = DefaultUrcMatcher::default();
+        let mut buf = Vec::<u8, TEST_RX_BUF_LEN>::new();
+
+        // Partial words are kept until the line is complete
+        buf.extend_from_slice(b"NO CA").unwrap();
+        assert_eq!(
+            digester.digest(&mut buf, &mut urc_matcher),
+            DigestResult::None
+        );
+        assert_eq!(
+            buf,
+            Vec::<_, TEST_RX_BUF_LEN>::from_slice(b"NO CA").unwrap()
+        );
+
+        buf.extend_from_slice(b"RRIER\r\nRING\r\n").unwrap();
+        assert_eq!(
+            digester.digest(&mut buf, &mut urc_matcher),
+            DigestResult::Urc(Vec::from_slice(b"NO CARRIER").unwrap())
+        );
+        assert_eq!(
+            digester.digest(&mut buf, &mut urc_matcher),
+            DigestResult::Urc(Vec::from_slice(b"RING").unwrap())
+        );
+        assert_eq!(buf, Vec::<_, TEST_RX_BUF_LEN>::new());
+
+        // Complete words wait for the end of the line
+        buf.extend_from_slice(b"RING").unwrap();
+        assert_eq!(
+            digester.digest(&mut buf, &mut urc_matcher),
+            DigestResult::None
+        );
+        buf.extend_from_slice(b"\r\n").unwrap();
+        assert_eq!(
+            digester.digest(&mut buf, &mut urc_matcher),
+            DigestResult::Urc(Vec::from_slice(b"RING").unwrap())
+        );
+
+        // Unknown words are still cleared, including longer words starting
+        // with a known word
+        buf.extend_from_slice(b"RDY\r\n").unwrap();
+        assert_eq!(
+            digester.digest(&mut buf, &mut urc_matcher),
+            DigestResult::None
+        );
+        assert_eq!(buf, Vec::<_, TEST_RX_BUF_LEN>::new());
+
+        buf.extend_from_slice(b"RINGING\r\n").unwrap();
+        assert_eq!(
+            digester.digest(&mut buf, &mut urc_matcher),
+            DigestResult::None
+        );
+        assert_eq!(buf, Vec::<_, TEST_RX_BUF_LEN>::new());
+    }
+
     #[test]
     fn read_error() {
         let mut digester = DefaultDigester::default();
@@ -410,7 +549,7 @@ mod test {
         buf.extend_from_slice(b"AT+USORD=3,16\r\n").unwrap();
         assert_eq!(
             digester.digest(&mut buf, &mut urc_matcher),
-            DigestResult::None
+            DigestResult::Echo(Vec::from_slice(b"AT+USORD=3,16\r\n").unwrap())
         );
         assert_eq!(digester.state, State::ReceivingResponse);
 
@@ -435,6 +574,34 @@ mod test {
         );
     }
 
+    #[test]
+    fn long_error_response() {
+        let mut digester = DefaultDigester::default();
+        let mut urc_matcher = DefaultUrcMatcher::default();
+        let mut buf = Vec::<u8, TEST_RX_BUF_LEN>::new();
+
+        let mut message = Vec::<u8, 100>::new();
+        message.resize(100, b'e').unwrap();
+
+        buf.extend_from_slice(b"AT+CMD\r\n").unwrap();
+        assert_eq!(
+            digester.digest(&mut buf, &mut urc_matcher),
+            DigestResult::Echo(Vec::from_slice(b"AT+CMD\r\n").unwrap())
+        );
+
+        buf.extend_from_slice(b"+CME ERROR: ").unwrap();
+        buf.extend_from_slice(&message).unwrap();
+        buf.extend_from_slice(b"\r\n").unwrap();
+
+        // The message is truncated, rather than dropped
+        let mut expected = Vec::<u8, MAX_ERROR_LEN>::from_slice(b"+CME ERROR: ").unwrap();
+        expected.resize(MAX_ERROR_LEN, b'e').unwrap();
+        assert_eq!(
+            digester.digest(&mut buf, &mut urc_matcher),
+            DigestResult::Response(Err(InternalError::Error(expected)))
+        );
+    }
+
     /// By breaking up non-AT-commands into chunks, it's possible that
     /// they're mistaken for AT commands due to buffer clearing.
     ///
@@ -475,10 +642,12 @@ mod test {
 
         for byte in b"AT\r\n" {
             buf.extend_from_slice(&[*byte]).unwrap();
-            assert_eq!(
-                digester.digest(&mut buf, &mut urc_matcher),
+            let expected = if *byte == b'\r' {
+                DigestResult::Echo(Vec::from_slice(b"AT\r").unwrap())
+            } else {
                 DigestResult::None
-            );
+            };
+            assert_eq!(digester.digest(&mut buf, &mut urc_matcher), expected);
         }
         assert_eq!(digester.state, State::ReceivingResponse);
     }
@@ -503,7 +672,7 @@ mod test {
         buf.extend_from_slice(b"AT+GMR\r\r\n").unwrap();
         assert_eq!(
             digester.digest(&mut buf, &mut urc_matcher),
-            DigestResult::None
+            DigestResult::Echo(Vec::from_slice(b"AT+GMR\r\r\n").unwrap())
         );
         assert_eq!(digester.state, State::ReceivingResponse);
     }
@@ -526,7 +695,7 @@ mod test {
         );
         assert_eq!(
             digester.digest(&mut buf, &mut urc_matcher),
-            DigestResult::None
+            DigestResult::Echo(Vec::from_slice(b"AT+GMR\r\r\n").unwrap())
         );
         assert_eq!(digester.state, State::ReceivingResponse);
     }
@@ -677,7 +846,7 @@ mod test {
         buf.extend_from_slice(b"AT+USORD=3,16\r\n").unwrap();
         assert_eq!(
             digester.digest(&mut buf, &mut urc_matcher),
-            DigestResult::None
+            DigestResult::Echo(Vec::from_slice(b"AT+USORD=3,16\r\n").unwrap())
         );
         assert_eq!(digester.state, State::ReceivingResponse);
 
@@ -712,7 +881,7 @@ mod test {
         buf.extend_from_slice(b"AT+USORD=3,16\r\n").unwrap();
         assert_eq!(
             digester.digest(&mut buf, &mut urc_matcher),
-            DigestResult::None
+            DigestResult::Echo(Vec::from_slice(b"AT+USORD=3,16\r\n").unwrap())
         );
         assert_eq!(digester.state, State::ReceivingResponse);
 
@@ -748,7 +917,7 @@ mod test {
         buf.extend_from_slice(b"AT+USORD=3,16\r\n").unwrap();
         assert_eq!(
             digester.digest(&mut buf, &mut urc_matcher),
-            DigestResult::None
+            DigestResult::Echo(Vec::from_slice(b"AT+USORD=3,16\r\n").unwrap())
         );
         assert_eq!(digester.state, State::ReceivingResponse);
 
@@ -787,7 +956,7 @@ mod test {
             .unwrap();
         assert_eq!(
             digester.digest(&mut buf, &mut urc_matcher),
-            DigestResult::None
+            DigestResult::Echo(Vec::from_slice(b"AT+USECMNG=0,0,\"Verisign\",1758\r").unwrap())
         );
         assert_eq!(digester.state, State::ReceivingResponse);
 
@@ -795,7 +964,7 @@ mod test {
 
         assert_eq!(digester.state, State::Idle);
         assert_eq!(buf, Vec::<_, TEST_RX_BUF_LEN>::new());
-        assert_eq!(result, DigestResult::Response(Ok(heapless::Vec::new())));
+        assert_eq!(result, DigestResult::Prompt);
     }
 
     // Regression test for #87
@@ -811,7 +980,7 @@ mod test {
 
         assert_eq!(
             digester.digest(&mut buf, &mut urc_matcher),
-            DigestResult::None
+            DigestResult::Echo(Vec::from_slice(b"AT+CPIN?\r\r\n").unwrap())
         );
         assert_eq!(digester.state, State::ReceivingResponse);
         assert_eq!(
@@ -842,7 +1011,7 @@ mod test {
 
         assert_eq!(
             digester.digest(&mut buf, &mut urc_matcher),
-            DigestResult::None
+            DigestResult::Echo(Vec::from_slice(b"AT+CPIN?\r\r\n").unwrap())
         );
         assert_eq!(digester.state, State::ReceivingResponse);
         assert_eq!(
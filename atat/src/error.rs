@@ -1,5 +1,9 @@
 use heapless::Vec;
 
+/// Maximum length of the error message kept by [`InternalError::Error`].
+/// Longer messages are truncated.
+pub const MAX_ERROR_LEN: usize = 85;
+
 /// Errors returned used internally within the crate
 #[derive(Clone, Debug, PartialEq)]
 pub enum InternalError {
@@ -17,8 +21,24 @@ pub enum InternalError {
     Overflow,
     /// Failed to parse received response
     Parse,
-    /// Error response containing any error message
-    Error(Vec<u8, 85>),
+    /// The device restarted
+    DeviceReset,
+    /// The device prompted for the payload of a command with a data phase,
+    /// eg. with `>`. Handled by the client, and never passed to
+    /// [`AtatCmd::parse`].
+    ///
+    /// [`AtatCmd::parse`]: trait.AtatCmd.html#tymethod.parse
+    Prompt,
+    /// Nothing was received in response to the current command, as reported
+    /// on a [`Command::CheckReceived`]. Handled by the client, and never
+    /// passed to [`AtatCmd::parse`].
+    ///
+    /// [`Command::CheckReceived`]: enum.Command.html#variant.CheckReceived
+    /// [`AtatCmd::parse`]: trait.AtatCmd.html#tymethod.parse
+    NothingReceived,
+    /// Error response containing any error message, truncated to
+    /// [`MAX_ERROR_LEN`] bytes
+    Error(Vec<u8, MAX_ERROR_LEN>),
 }
 
 #[cfg(feature = "defmt")]
@@ -32,6 +52,9 @@ impl defmt::Format for InternalError {
             InternalError::Aborted => defmt::write!(f, "Aborted"),
             InternalError::Overflow => defmt::write!(f, "Overflow"),
             InternalError::Parse => defmt::write!(f, "Parse"),
+            InternalError::DeviceReset => defmt::write!(f, "DeviceReset"),
+            InternalError::Prompt => defmt::write!(f, "Prompt"),
+            InternalError::NothingReceived => defmt::write!(f, "NothingReceived"),
             InternalError::Error(e) => defmt::write!(f, "Error({=[u8]:a})", &e),
         }
     }
@@ -55,6 +78,10 @@ pub enum Error<E = GenericError> {
     Overflow,
     /// Failed to parse received response
     Parse,
+    /// Failed to serialize command
+    Serialize,
+    /// The device restarted while waiting for a response
+    DeviceReset,
     /// Error response containing any error message
     Error(E),
 }
@@ -72,6 +99,9 @@ where
             InternalError::Aborted => Self::Aborted,
             InternalError::Overflow => Self::Overflow,
             InternalError::Parse => Self::Parse,
+            InternalError::DeviceReset => Self::DeviceReset,
+            InternalError::Prompt => Self::InvalidResponse,
+            InternalError::NothingReceived => Self::Timeout,
             InternalError::Error(ref e) => {
                 if let Ok(s) = core::str::from_utf8(&e.clone()) {
                     if let Ok(e) = core::str::FromStr::from_str(s) {
@@ -1,13 +1,16 @@
 use heapless::Vec;
 
 use crate::atat_log;
+use crate::clock::Instant;
 use crate::error::InternalError;
-use crate::helpers::LossyStr;
+use crate::helpers::{LossyStr, SliceExt};
+use crate::observer::{Event, Observer};
+use crate::payload::{PayloadHeader, PayloadSink};
 use crate::queues::{ComConsumer, ResProducer, UrcProducer};
 use crate::Command;
 use crate::{
     digest::{DefaultDigester, DigestResult, Digester},
-    urc_matcher::{DefaultUrcMatcher, UrcMatcher},
+    urc_matcher::{find_payload, DefaultUrcMatcher, PayloadPosition, PayloadSearch, UrcMatcher},
 };
 
 pub struct IngressManager<D, U, const BUF_LEN: usize, const URC_CAPACITY: usize>
@@ -30,6 +33,41 @@ where
 
     /// URC matcher.
     urc_matcher: U,
+
+    /// Time of the last write, if timestamped through [`write_at`]
+    ///
+    /// [`write_at`]: #method.write_at
+    last_write: Option<Instant>,
+    /// Time in ms without incoming data, after which partially received data
+    /// is flushed
+    silence_timeout: Option<u32>,
+    /// Lines printed by the device when it restarts, eg. `RDY`
+    reset_markers: &'static [&'static [u8]],
+
+    /// Observer of received data
+    observer: Option<&'static dyn Observer>,
+    /// Latest known time, used to timestamp events
+    now: Option<Instant>,
+
+    /// Headers of lines carrying a payload to stream to `payload_sink`
+    payload_headers: &'static [PayloadHeader],
+    /// Receiver of streamed payloads
+    payload_sink: Option<&'static dyn PayloadSink>,
+    /// Payload currently being streamed
+    payload: Option<Payload>,
+    /// Lines starting before this index have already been checked for
+    /// payload headers
+    payload_scanned: usize,
+}
+
+/// State of the payload currently being streamed
+struct Payload {
+    /// Header the payload was announced by
+    header: &'static PayloadHeader,
+    /// Number of payload bytes left to stream
+    remaining: usize,
+    /// Whether a closing quote follows the payload
+    quoted: bool,
 }
 
 impl<const BUF_LEN: usize, const URC_CAPACITY: usize>
@@ -71,9 +109,73 @@ where
             com_c,
             urc_matcher,
             digester,
+            last_write: None,
+            silence_timeout: None,
+            reset_markers: &[],
+            observer: None,
+            now: None,
+            payload_headers: &[],
+            payload_sink: None,
+            payload: None,
+            payload_scanned: 0,
         }
     }
 
+    /// Report received data to `observer`
+    #[must_use]
+    pub fn with_observer(mut self, observer: &'static dyn Observer) -> Self {
+        self.observer = Some(observer);
+        self
+    }
+
+    fn observe(&self, event: Event<'_>) {
+        if let Some(observer) = self.observer {
+            observer.on_event(&event);
+        }
+    }
+
+    /// Stream the payloads of lines starting with any of `headers` to `sink`,
+    /// instead of buffering them. See the [`payload`] module.
+    ///
+    /// [`payload`]: payload/index.html
+    #[must_use]
+    pub fn with_payload_sink(
+        mut self,
+        headers: &'static [PayloadHeader],
+        sink: &'static dyn PayloadSink,
+    ) -> Self {
+        self.payload_headers = headers;
+        self.payload_sink = Some(sink);
+        self
+    }
+
+    /// Detect the device restarting by any of the lines in `markers`, eg.
+    /// `RDY` or a boot banner. When detected, the buffer is cleared and the
+    /// client is notified, failing any command awaiting a response with
+    /// [`Error::DeviceReset`].
+    ///
+    /// [`Error::DeviceReset`]: enum.Error.html#variant.DeviceReset
+    #[must_use]
+    pub fn with_reset_markers(mut self, markers: &'static [&'static [u8]]) -> Self {
+        self.reset_markers = markers;
+        self
+    }
+
+    /// Flush partially received data after `ms` milliseconds without incoming
+    /// data, allowing recovery from eg. a modem resetting mid-response, or
+    /// noise on the line.
+    ///
+    /// This requires incoming data to be timestamped with [`write_at`], and
+    /// the timeout to be checked by calling [`digest_at`].
+    ///
+    /// [`write_at`]: #method.write_at
+    /// [`digest_at`]: #method.digest_at
+    #[must_use]
+    pub fn with_silence_timeout(mut self, ms: u32) -> Self {
+        self.silence_timeout = Some(ms);
+        self
+    }
+
     /// Write data into the internal buffer raw bytes being the core type allows
     /// the ingress manager to be abstracted over the communication medium.
     ///
@@ -82,11 +184,152 @@ where
     /// ingress manager receive buffer.
     pub fn write(&mut self, data: &[u8]) {
         atat_log!(trace, "Write: \"{:?}\"", LossyStr(data));
+        self.observe(Event::DataReceived {
+            at: self.now,
+            bytes: data,
+        });
+
+        if self.payload_sink.is_none() {
+            self.extend(data);
+            return;
+        }
+
+        // Alternate between buffering data until a payload header is
+        // complete, and streaming the payload following it
+        let mut data = self.stream_payload(data);
+        while !data.is_empty() {
+            // Report any data not fitting in a full buffer as overflowing
+            let len = match BUF_LEN - self.buf.len() {
+                0 => data.len(),
+                free => free.min(data.len()),
+            };
+            if !self.extend(&data[..len]) {
+                return;
+            }
+            self.extract_payload();
+            data = self.stream_payload(&data[len..]);
+        }
+    }
 
+    /// Append `data` to the buffer, returning false if it overflowed
+    fn extend(&mut self, data: &[u8]) -> bool {
         if self.buf.extend_from_slice(data).is_err() {
             atat_log!(error, "OVERFLOW DATA! Buffer: {:?}", LossyStr(&self.buf));
+            self.observe(Event::Overflow {
+                at: self.now,
+                bytes: data,
+            });
             self.notify_response(Err(InternalError::Overflow));
+            return false;
         }
+        true
+    }
+
+    /// Forward the start of `data` to the payload sink while streaming a
+    /// payload, returning the remaining data
+    fn stream_payload<'d>(&mut self, mut data: &'d [u8]) -> &'d [u8] {
+        let (payload, sink) = match (&mut self.payload, self.payload_sink) {
+            (Some(payload), Some(sink)) => (payload, sink),
+            _ => return data,
+        };
+
+        let len = payload.remaining.min(data.len());
+        if len > 0 {
+            sink.write(&data[..len]);
+            payload.remaining -= len;
+            data = &data[len..];
+            if payload.remaining == 0 {
+                sink.end(payload.header);
+            }
+        }
+        if payload.remaining == 0 && !(payload.quoted && data.is_empty()) {
+            if payload.quoted {
+                data = data.strip_prefix(b"\"").unwrap_or(data);
+            }
+            self.payload = None;
+        }
+        data
+    }
+
+    /// Look for a complete payload header in the buffer, and start streaming
+    /// the payload following it, leaving only the header in the buffer
+    fn extract_payload(&mut self) {
+        let sink = match self.payload_sink {
+            Some(sink) => sink,
+            None => return,
+        };
+        while self.payload.is_none() {
+            let (start, header, position) = match self.find_payload_header() {
+                Some(found) => found,
+                None => return,
+            };
+            let header_end = start + position.len_end;
+            atat_log!(
+                debug,
+                "Streaming payload of {} bytes [{:?}]",
+                position.len,
+                LossyStr(&self.buf[start..header_end])
+            );
+            sink.start(header, &self.buf[start..header_end], position.len);
+            if position.len == 0 {
+                sink.end(header);
+            }
+
+            // Keep the line ending of the header for payloads on the next
+            // line, dropping only the separator of quoted and raw payloads
+            let keep = if self.buf[header_end..].starts_with(b"\r\n") {
+                start + position.start
+            } else {
+                header_end
+            };
+            let buf = core::mem::replace(&mut self.buf, Vec::new());
+            self.buf = Vec::from_slice(&buf[..keep]).unwrap();
+            self.payload_scanned = header_end;
+            self.payload = Some(Payload {
+                header,
+                remaining: position.len,
+                quoted: position.quoted,
+            });
+            let rest = self.stream_payload(&buf[start + position.start..]);
+            self.buf.extend_from_slice(rest).unwrap();
+        }
+    }
+
+    /// Find the first unhandled line in the buffer starting with a complete
+    /// payload header, returning its start, header and payload position
+    fn find_payload_header(&self) -> Option<(usize, &'static PayloadHeader, PayloadPosition)> {
+        let line_starts = core::iter::once(0).chain(
+            self.buf
+                .iter()
+                .enumerate()
+                .filter(|(_, &c)| c == D::FORMAT_CHAR)
+                .map(|(i, _)| i + 1),
+        );
+
+        for start in line_starts.filter(|&start| start >= self.payload_scanned) {
+            let line = &self.buf[start..];
+            for header in self.payload_headers {
+                if !line.starts_with(header.prefix) {
+                    continue;
+                }
+                if let PayloadSearch::Found(position) =
+                    find_payload(line, header.len_field, header.next_line)
+                {
+                    return Some((start, header, position));
+                }
+            }
+        }
+        None
+    }
+
+    /// Same as [`write`], but timestamps the data to allow detecting silence
+    /// timeouts.
+    ///
+    /// [`write`]: #method.write
+    pub fn write_at(&mut self, data: &[u8], now: Instant) {
+        self.last_write = Some(now);
+        self.now = Some(now);
+        self.write(data);
     }
 
     /// Return the current length of the internal buffer
@@ -122,8 +365,13 @@ where
                     atat_log!(debug, "Received response: \"{:?}\"", LossyStr(r));
                 }
             }
+            Err(InternalError::Prompt) => atat_log!(debug, "Received prompt"),
             Err(e) => atat_log!(error, "Received error response {:?}", e),
         }
+        self.observe(Event::Response {
+            at: self.now,
+            result: resp.as_deref(),
+        });
         if self.res_p.ready() {
             unsafe { self.res_p.enqueue_unchecked(resp) };
         } else {
@@ -136,6 +384,10 @@ where
     /// received
     fn notify_urc(&mut self, resp: Vec<u8, BUF_LEN>) {
         atat_log!(debug, "Received response: \"{:?}\"", LossyStr(&resp));
+        self.observe(Event::UrcReceived {
+            at: self.now,
+            bytes: &resp,
+        });
 
         if self.urc_p.ready() {
             unsafe { self.urc_p.enqueue_unchecked(resp) };
@@ -149,34 +401,241 @@ where
     fn handle_com(&mut self) {
         if let Some(com) = self.com_c.dequeue() {
             match com {
-                Command::Reset => {
+                Command::Reset | Command::Timeout => {
                     atat_log!(
                         debug,
                         "Cleared complete buffer as requested by client [{:?}]",
                         LossyStr(&self.buf)
                     );
+                    let (at, bytes) = (self.now, &self.buf[..]);
+                    self.observe(if com == Command::Timeout {
+                        Event::Timeout { at, bytes }
+                    } else {
+                        Event::BufferCleared { at, bytes }
+                    });
                     self.digester.reset();
                     self.buf.clear();
+                    self.payload = None;
+                    self.payload_scanned = 0;
                 }
                 Command::ForceReceiveState => self.digester.force_receive_state(),
+                Command::CheckReceived => {
+                    // A complete response still in the queue was received too
+                    if !self.digester.awaiting_response()
+                        && self.buf.is_empty()
+                        && self.res_p.ready()
+                    {
+                        atat_log!(debug, "Nothing received for the current command");
+                        unsafe {
+                            self.res_p
+                                .enqueue_unchecked(Err(InternalError::NothingReceived))
+                        };
+                    }
+                }
             }
         }
     }
 
+    /// Same as [`digest`], but afterwards flushes any partially received data
+    /// left in the buffer if the silence timeout has expired.
+    ///
+    /// If a response was being received, the client is notified with a
+    /// timeout error.
+    ///
+    /// [`digest`]: #method.digest
+    pub fn digest_at(&mut self, now: Instant) {
+        self.now = Some(now);
+        self.digest();
+        self.handle_silence(now);
+    }
+
+    /// Flush stale, partially received data on silence timeout
+    fn handle_silence(&mut self, now: Instant) {
+        let (timeout, last_write) = match (self.silence_timeout, self.last_write) {
+            (Some(timeout), Some(last_write)) => (timeout, last_write),
+            _ => return,
+        };
+        if now.millis_since(last_write) < u64::from(timeout) {
+            return;
+        }
+
+        self.last_write = None;
+        let awaiting_response = self.digester.awaiting_response();
+        if !self.buf.is_empty() {
+            atat_log!(
+                warn,
+                "Silence timeout, flushing stale data [{:?}]",
+                LossyStr(&self.buf)
+            );
+            self.observe(Event::BufferCleared {
+                at: self.now,
+                bytes: &self.buf,
+            });
+            if awaiting_response {
+                self.notify_response(Err(InternalError::Timeout));
+            }
+            self.buf.clear();
+            self.digester.reset();
+            self.payload = None;
+            self.payload_scanned = 0;
+        } else if !awaiting_response {
+            // Clear any state left over from partially discarded lines, while
+            // letting slow commands keep waiting for their response
+            self.digester.reset();
+        }
+    }
+
+    /// Look for a reset marker making up a complete line in the buffer
+    fn find_reset_marker(&self) -> ResetMarker {
+        let line_starts = core::iter::once(0).chain(
+            self.buf
+                .iter()
+                .enumerate()
+                .filter(|(_, &c)| c == D::FORMAT_CHAR)
+                .map(|(i, _)| i + 1),
+        );
+
+        for start in line_starts {
+            let line = &self.buf[start..];
+            if line.is_empty() {
+                continue;
+            }
+            let complete = line
+                .iter()
+                .any(|&c| c == D::LINE_TERM_CHAR || c == D::FORMAT_CHAR);
+            for marker in self.reset_markers {
+                if !complete {
+                    if marker.starts_with(line) {
+                        return ResetMarker::Partial { start };
+                    }
+                } else if line.starts_with(marker) {
+                    match line.get(marker.len()) {
+                        Some(&c) if c == D::LINE_TERM_CHAR || c == D::FORMAT_CHAR => {
+                            return ResetMarker::Found {
+                                start,
+                                end: start + marker.len(),
+                            }
+                        }
+                        _ => {}
+                    }
+                }
+            }
+        }
+        ResetMarker::None
+    }
+
+    /// Handle the device restarting, discarding the buffer up to `end`
+    fn device_reset(&mut self, end: usize) {
+        atat_log!(
+            warn,
+            "Device reset detected, clearing buffer [{:?}]",
+            LossyStr(&self.buf[..end])
+        );
+        self.observe(Event::BufferCleared {
+            at: self.now,
+            bytes: &self.buf[..end],
+        });
+        let rest = self.buf[end..].trim_start(&[D::LINE_TERM_CHAR, D::FORMAT_CHAR]);
+        self.buf = Vec::from_slice(rest).unwrap();
+        self.digester.reset();
+        self.payload = None;
+        self.payload_scanned = 0;
+        self.notify_response(Err(InternalError::DeviceReset));
+    }
+
+    /// Run the digester once, returning whether any data was consumed
+    fn digest_once(&mut self) -> bool {
+        let len = self.buf.len();
+        self.digest_until(len).unwrap_or(true)
+    }
+
+    /// Run the digester once, returning whether any data was consumed.
+    ///
+    /// Results consuming more than the first `limit` bytes of the buffer, eg.
+    /// a response running into a reset marker, are discarded, returning the
+    /// number of consumed bytes as an error.
+    fn digest_until(&mut self, limit: usize) -> Result<bool, usize> {
+        let len = self.buf.len();
+        let result = self.digester.digest(&mut self.buf, &mut self.urc_matcher);
+        let consumed = len.saturating_sub(self.buf.len());
+
+        // Keep track of handled payload headers, as the buffer is consumed
+        self.payload_scanned = self.payload_scanned.saturating_sub(consumed);
+
+        if consumed > limit {
+            atat_log!(warn, "Discarding data running into a reset marker");
+            return Err(consumed);
+        }
+
+        Ok(match result {
+            DigestResult::None => self.buf.len() < len,
+            DigestResult::Echo(echo) => {
+                self.observe(Event::EchoReceived {
+                    at: self.now,
+                    bytes: &echo,
+                });
+                true
+            }
+            DigestResult::Urc(urc_line) => {
+                self.notify_urc(urc_line);
+                true
+            }
+            DigestResult::Response(resp) => {
+                self.notify_response(resp);
+                true
+            }
+            DigestResult::Prompt => {
+                self.notify_response(Err(InternalError::Prompt));
+                true
+            }
+        })
+    }
+
     pub fn digest(&mut self) {
         for _ in 0..5 {
             // Handle commands every loop to catch timeouts asap
             self.handle_com();
 
-            match self.digester.digest(&mut self.buf, &mut self.urc_matcher) {
-                DigestResult::None => {}
-                DigestResult::Urc(urc_line) => self.notify_urc(urc_line),
-                DigestResult::Response(resp) => self.notify_response(resp),
-            };
+            if self.reset_markers.is_empty() {
+                self.digest_once();
+                continue;
+            }
+
+            // Digest complete lines received before a reset marker, before
+            // handling the reset
+            match self.find_reset_marker() {
+                // Wait for the rest of a possible marker
+                ResetMarker::Partial { start: 0 } => {}
+                ResetMarker::None | ResetMarker::Partial { .. } => {
+                    self.digest_once();
+                }
+                ResetMarker::Found { start: 0, end } => self.device_reset(end),
+                ResetMarker::Found { start, end } => match self.digest_until(start) {
+                    Ok(true) => {}
+                    Ok(false) => self.device_reset(end),
+                    // The marker was consumed along with the discarded data
+                    Err(consumed) => self.device_reset(end.saturating_sub(consumed)),
+                },
+            }
         }
     }
 }
 
+/// Result of looking for a reset marker in the buffer
+enum ResetMarker {
+    None,
+    /// The buffer ends with the start of a marker, on the line starting at
+    /// `start`
+    Partial {
+        start: usize,
+    },
+    /// A marker was found on the line starting at `start`, ending at `end`
+    Found {
+        start: usize,
+        end: usize,
+    },
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
@@ -211,4 +670,339 @@ mod test {
         ingress.digest();
         assert_eq!(res_c.dequeue().unwrap(), Err(InternalError::Overflow));
     }
+
+    #[test]
+    fn silence_timeout() {
+        static mut RES_Q: ResQueue<TEST_RX_BUF_LEN> = Queue::new();
+        let (res_p, mut res_c) = unsafe { RES_Q.split() };
+        static mut URC_Q: UrcQueue<TEST_RX_BUF_LEN, TEST_URC_CAPACITY> = Queue::new();
+        let (urc_p, mut urc_c) = unsafe { URC_Q.split() };
+        static mut COM_Q: ComQueue = Queue::new();
+        let (_com_p, com_c) = unsafe { COM_Q.split() };
+
+        let mut ingress = IngressManager::with_customs(
+            res_p,
+            urc_p,
+            com_c,
+            DefaultUrcMatcher::default(),
+            DefaultDigester::default(),
+        )
+        .with_silence_timeout(50);
+
+        // Garbage while idle is dropped without notifying the client, and does
+        // not swallow the next line after a silence
+        ingress.write_at(b"\x00\xffboot", Instant::from_millis(0));
+        ingress.digest_at(Instant::from_millis(10));
+        ingress.digest_at(Instant::from_millis(60));
+        ingress.write_at(b"+CREG: 1\r\n", Instant::from_millis(70));
+        ingress.digest_at(Instant::from_millis(70));
+        assert_eq!(&urc_c.dequeue().unwrap()[..], b"+CREG: 1");
+        assert!(res_c.dequeue().is_none());
+
+        // Partial response notifies the client
+        ingress.write_at(b"AT+CGMR\r\n", Instant::from_millis(100));
+        ingress.digest_at(Instant::from_millis(100));
+        ingress.write_at(b"+CGMR: 1.2", Instant::from_millis(120));
+        ingress.digest_at(Instant::from_millis(160));
+        assert!(res_c.dequeue().is_none());
+        ingress.digest_at(Instant::from_millis(170));
+        assert_eq!(res_c.dequeue().unwrap(), Err(InternalError::Timeout));
+        assert!(ingress.is_empty());
+
+        // The digester is back to idle, and handles the next response
+        ingress.write_at(b"AT\r\r\nOK\r\n", Instant::from_millis(200));
+        ingress.digest_at(Instant::from_millis(300));
+        assert_eq!(res_c.dequeue().unwrap(), Ok(Vec::new()));
+    }
+
+    #[test]
+    fn reset_markers() {
+        static mut RES_Q: ResQueue<TEST_RX_BUF_LEN> = Queue::new();
+        let (res_p, mut res_c) = unsafe { RES_Q.split() };
+        static mut URC_Q: UrcQueue<TEST_RX_BUF_LEN, TEST_URC_CAPACITY> = Queue::new();
+        let (urc_p, mut urc_c) = unsafe { URC_Q.split() };
+        static mut COM_Q: ComQueue = Queue::new();
+        let (_com_p, com_c) = unsafe { COM_Q.split() };
+
+        let mut ingress = IngressManager::with_customs(
+            res_p,
+            urc_p,
+            com_c,
+            DefaultUrcMatcher::default(),
+            DefaultDigester::default(),
+        )
+        .with_reset_markers(&[b"RDY", b"SYSTEM START"]);
+
+        // Reset in the middle of a response
+        ingress.write(b"AT+CGMR\r\n+CGMR: 1.");
+        ingress.digest();
+        ingress.write(b"\x00\x00\r\nRDY\r\n+CFUN: 1\r\n");
+        ingress.digest();
+        assert_eq!(res_c.dequeue().unwrap(), Err(InternalError::DeviceReset));
+        assert!(res_c.dequeue().is_none());
+        assert_eq!(&urc_c.dequeue().unwrap()[..], b"+CFUN: 1");
+        assert!(ingress.is_empty());
+
+        // Marker split across writes is not discarded as garbage
+        ingress.write(b"SYSTEM");
+        ingress.digest();
+        assert!(res_c.dequeue().is_none());
+        ingress.write(b" START\r\n");
+        ingress.digest();
+        assert_eq!(res_c.dequeue().unwrap(), Err(InternalError::DeviceReset));
+
+        // Lines only starting like a marker are not affected
+        ingress.write(b"AT\r\r\nOK\r\n");
+        ingress.digest();
+        assert_eq!(res_c.dequeue().unwrap(), Ok(Vec::new()));
+    }
+
+    #[test]
+    fn reset_marker_after_lines() {
+        static mut RES_Q: ResQueue<TEST_RX_BUF_LEN> = Queue::new();
+        let (res_p, mut res_c) = unsafe { RES_Q.split() };
+        static mut URC_Q: UrcQueue<TEST_RX_BUF_LEN, TEST_URC_CAPACITY> = Queue::new();
+        let (urc_p, mut urc_c) = unsafe { URC_Q.split() };
+        static mut COM_Q: ComQueue = Queue::new();
+        let (_com_p, com_c) = unsafe { COM_Q.split() };
+
+        let mut ingress =
+            IngressManager::new(res_p, urc_p, com_c).with_reset_markers(&[b"RDY", b"SYSTEM START"]);
+
+        // URC received in the same write as the marker
+        ingress.write(b"+CREG: 1\r\nRDY\r\n");
+        ingress.digest();
+        assert_eq!(&urc_c.dequeue().unwrap()[..], b"+CREG: 1");
+        assert_eq!(res_c.dequeue().unwrap(), Err(InternalError::DeviceReset));
+        assert!(ingress.is_empty());
+
+        // Response followed by an unterminated marker
+        ingress.write(b"AT+CGMR\r\r\n+CGMR: 1.2\r\nOK\r\nRDY");
+        ingress.digest();
+        assert_eq!(
+            res_c.dequeue().unwrap(),
+            Ok(Vec::from_slice(b"+CGMR: 1.2").unwrap())
+        );
+        assert!(res_c.dequeue().is_none());
+        ingress.write(b"\r\n");
+        ingress.digest();
+        assert_eq!(res_c.dequeue().unwrap(), Err(InternalError::DeviceReset));
+
+        // Partial marker does not hold back the lines before it
+        ingress.write(b"+CREG: 2\r\nSYST");
+        ingress.digest();
+        assert_eq!(&urc_c.dequeue().unwrap()[..], b"+CREG: 2");
+        assert!(res_c.dequeue().is_none());
+
+        // Line only starting with a marker, once complete
+        ingress.write(b"EM STARTED\r\nRDY");
+        ingress.digest();
+        ingress.write(b"X\r\n");
+        ingress.digest();
+        assert!(res_c.dequeue().is_none());
+
+        // Response running into a marker is discarded with the reset
+        ingress.write(b"AT+CGMR\r\r\n+CGMR: 1.2\r\nRDY\r\nOK\r\n");
+        ingress.digest();
+        assert_eq!(res_c.dequeue().unwrap(), Err(InternalError::DeviceReset));
+        assert!(res_c.dequeue().is_none());
+        assert!(urc_c.dequeue().is_none());
+    }
+
+    /// Observer recording events as strings, as they borrow the ingress buffer
+    struct Recorder(std::sync::Mutex<std::vec::Vec<std::string::String>>);
+
+    impl Observer for Recorder {
+        fn on_event(&self, event: &Event<'_>) {
+            let s = match event {
+                Event::DataReceived { .. } => return,
+                Event::EchoReceived { bytes, .. } => format!("echo {:?}", LossyStr(bytes)),
+                Event::Timeout { at, bytes } => {
+                    format!("timeout {:?} at {:?}", LossyStr(bytes), at)
+                }
+                Event::Response {
+                    result: Ok(bytes), ..
+                } => format!("response {:?}", LossyStr(bytes)),
+                Event::Response { result: Err(e), .. } => format!("error {:?}", e),
+                Event::UrcReceived { bytes, .. } => format!("urc {:?}", LossyStr(bytes)),
+                Event::BufferCleared { at, bytes } => {
+                    format!("cleared {:?} at {:?}", LossyStr(bytes), at)
+                }
+                e => format!("{:?}", e),
+            };
+            self.0.lock().unwrap().push(s);
+        }
+    }
+
+    #[test]
+    fn observer() {
+        static mut RES_Q: ResQueue<TEST_RX_BUF_LEN> = Queue::new();
+        let (res_p, _res_c) = unsafe { RES_Q.split() };
+        static mut URC_Q: UrcQueue<TEST_RX_BUF_LEN, TEST_URC_CAPACITY> = Queue::new();
+        let (urc_p, _urc_c) = unsafe { URC_Q.split() };
+        static mut COM_Q: ComQueue = Queue::new();
+        let (mut com_p, com_c) = unsafe { COM_Q.split() };
+        static RECORDER: Recorder = Recorder(std::sync::Mutex::new(std::vec::Vec::new()));
+
+        let mut ingress = IngressManager::with_customs(
+            res_p,
+            urc_p,
+            com_c,
+            DefaultUrcMatcher::default(),
+            DefaultDigester::default(),
+        )
+        .with_silence_timeout(50)
+        .with_observer(&RECORDER);
+
+        ingress.write_at(
+            b"AT+CGMR\r\r\n+CGMR: 1.2\r\nOK\r\n",
+            Instant::from_millis(0),
+        );
+        ingress.digest_at(Instant::from_millis(0));
+        ingress.write_at(b"+CREG: 1\r\n", Instant::from_millis(10));
+        ingress.digest_at(Instant::from_millis(10));
+        ingress.write_at(b"AT+CGMR\r\r\nERROR\r\n", Instant::from_millis(20));
+        ingress.digest_at(Instant::from_millis(20));
+        ingress.write_at(b"+CGMR", Instant::from_millis(30));
+        ingress.digest_at(Instant::from_millis(90));
+        ingress.write_at(b"AT+CGMR\r\r\n+CG", Instant::from_millis(100));
+        ingress.digest_at(Instant::from_millis(100));
+        com_p.enqueue(Command::Timeout).unwrap();
+        ingress.digest_at(Instant::from_millis(1100));
+
+        assert_eq!(
+            *RECORDER.0.lock().unwrap(),
+            [
+                "echo \"AT+CGMR\\r\\r\\n\"",
+                "response \"+CGMR: 1.2\"",
+                "urc \"+CREG: 1\"",
+                "echo \"AT+CGMR\\r\\r\\n\"",
+                "error Error([69, 82, 82, 79, 82])",
+                "cleared \"+CGMR\" at Some(Instant(90))",
+                "echo \"AT+CGMR\\r\\r\\n\"",
+                "timeout \"+CG\" at Some(Instant(1100))",
+            ]
+        );
+    }
+
+    #[test]
+    fn ingress_is_send() {
+        // The ingress manager is moved into the UART interrupt
+        fn assert_send<T: Send>() {}
+        assert_send::<
+            IngressManager<DefaultDigester, DefaultUrcMatcher, TEST_RX_BUF_LEN, TEST_URC_CAPACITY>,
+        >();
+    }
+
+    /// Sink recording payloads, and the start and end of each payload
+    struct Sink(std::sync::Mutex<std::vec::Vec<u8>>);
+
+    impl PayloadSink for Sink {
+        fn start(&self, _header: &PayloadHeader, line: &[u8], len: usize) {
+            let mut data = self.0.lock().unwrap();
+            data.extend_from_slice(line);
+            data.extend_from_slice(format!(" ({}) [", len).as_bytes());
+        }
+
+        fn write(&self, data: &[u8]) {
+            self.0.lock().unwrap().extend_from_slice(data);
+        }
+
+        fn end(&self, header: &PayloadHeader) {
+            let mut data = self.0.lock().unwrap();
+            data.extend_from_slice(b"] ");
+            data.extend_from_slice(header.prefix);
+        }
+    }
+
+    impl Sink {
+        fn take(&self) -> std::vec::Vec<u8> {
+            core::mem::take(&mut *self.0.lock().unwrap())
+        }
+    }
+
+    #[test]
+    fn payload_sink() {
+        static mut RES_Q: ResQueue<TEST_RX_BUF_LEN> = Queue::new();
+        let (res_p, mut res_c) = unsafe { RES_Q.split() };
+        static mut URC_Q: UrcQueue<TEST_RX_BUF_LEN, TEST_URC_CAPACITY> = Queue::new();
+        let (urc_p, mut urc_c) = unsafe { URC_Q.split() };
+        static mut COM_Q: ComQueue = Queue::new();
+        let (_com_p, com_c) = unsafe { COM_Q.split() };
+        static SINK: Sink = Sink(std::sync::Mutex::new(std::vec::Vec::new()));
+        static HEADERS: [PayloadHeader; 3] = [
+            PayloadHeader::new(b"+USORD:", 1),
+            PayloadHeader::new(b"+USORF:", 3),
+            PayloadHeader::new(b"+QIRD:", 0).next_line(),
+        ];
+
+        let mut ingress =
+            IngressManager::new(res_p, urc_p, com_c).with_payload_sink(&HEADERS, &SINK);
+
+        // Payload larger than the buffer, containing a response code
+        let payload: std::vec::Vec<u8> = b"OK\r\n".iter().copied().cycle().take(1000).collect();
+        let mut data = b"AT+USORD=0,1000\r\r\n+USORD: 0,1000,\"".to_vec();
+        data.extend_from_slice(&payload);
+        data.extend_from_slice(b"\"\r\nOK\r\n");
+        for chunk in data.chunks(37) {
+            ingress.write(chunk);
+            ingress.digest();
+        }
+        assert_eq!(
+            res_c.dequeue(),
+            Some(Ok(Vec::from_slice(b"+USORD: 0,1000").unwrap()))
+        );
+        let mut expected = b"+USORD: 0,1000 (1000) [".to_vec();
+        expected.extend_from_slice(&payload);
+        expected.extend_from_slice(b"] +USORD:");
+        assert_eq!(SINK.take(), expected);
+        assert!(ingress.is_empty());
+
+        // Quoted payload containing a line ending
+        ingress.write(b"+USORF: 0,\"1.2.3.4\",5000,4,\"a\r\nb\"\r\n");
+        ingress.digest();
+        assert_eq!(
+            urc_c.dequeue(),
+            Some(Vec::from_slice(b"+USORF: 0,\"1.2.3.4\",5000,4").unwrap())
+        );
+        assert_eq!(
+            SINK.take(),
+            b"+USORF: 0,\"1.2.3.4\",5000,4 (4) [a\r\nb] +USORF:"
+        );
+        assert!(ingress.is_empty());
+
+        // Header without a payload, eg. `AT+USORD=0,0` querying the length
+        ingress.write(b"AT+USORD=0,0\r\r\n+USORD: 0,1024\r\n\r\nOK\r\n");
+        ingress.digest();
+        assert_eq!(
+            res_c.dequeue(),
+            Some(Ok(Vec::from_slice(b"+USORD: 0,1024").unwrap()))
+        );
+        assert!(SINK.take().is_empty());
+        assert!(ingress.is_empty());
+
+        // Payload on the next line, with the header split across writes
+        ingress.write(b"AT+QIRD=0\r\r\n+QIRD: ");
+        ingress.digest();
+        ingress.write(b"5\r\nhel");
+        ingress.digest();
+        ingress.write(b"lo\r\nOK\r\n");
+        ingress.digest();
+        assert_eq!(
+            res_c.dequeue(),
+            Some(Ok(Vec::from_slice(b"+QIRD: 5").unwrap()))
+        );
+        assert_eq!(SINK.take(), b"+QIRD: 5 (5) [hello] +QIRD:");
+        assert!(ingress.is_empty());
+
+        // Empty payload on the next line
+        ingress.write(b"AT+QIRD=0\r\r\n+QIRD: 0\r\n\r\nOK\r\n");
+        ingress.digest();
+        assert_eq!(
+            res_c.dequeue(),
+            Some(Ok(Vec::from_slice(b"+QIRD: 0").unwrap()))
+        );
+        assert_eq!(SINK.take(), b"+QIRD: 0 (0) [] +QIRD:");
+        assert!(ingress.is_empty());
+    }
 }
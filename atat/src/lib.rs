@@ -162,9 +162,12 @@
 //!         com_queue: unsafe { COM_QUEUE.split() },
 //!     };
 //!
+//!     // Any monotonic millisecond counter, eg. an RTIC monotonic
+//!     let clock = atat::clock::FugitClock::new(monotonics::now);
+//!
 //!     let (tx, rx) = serial.split();
 //!     let (mut client, ingress) =
-//!         ClientBuilder::new(tx, timer, atat::Config::new(atat::Mode::Timeout)).build(queues);
+//!         ClientBuilder::new(tx, clock, atat::Config::new(atat::Mode::Timeout)).build(queues);
 //!
 //!     unsafe { INGRESS = Some(ingress) };
 //!     unsafe { RX = Some(rx) };
@@ -231,14 +234,20 @@
 #![cfg_attr(all(not(test), not(feature = "std")), no_std)]
 
 mod builder;
+pub mod capture;
 mod client;
+pub mod clock;
 mod digest;
 mod error;
 pub mod helpers;
 mod ingress_manager;
+pub mod observer;
+pub mod payload;
 mod queues;
+pub mod sync;
 mod traits;
-mod urc_matcher;
+pub mod transport;
+pub mod urc_matcher;
 
 #[cfg(feature = "derive")]
 pub use atat_derive;
@@ -257,10 +266,10 @@ pub use heapless;
 pub use builder::ClientBuilder;
 pub use client::{Client, Mode};
 pub use digest::{DefaultDigester, DigestResult, Digester};
-pub use error::{Error, GenericError, InternalError};
+pub use error::{Error, GenericError, InternalError, MAX_ERROR_LEN};
 pub use ingress_manager::IngressManager;
 pub use queues::{ComQueue, Queues, ResQueue, UrcQueue};
-pub use traits::{AtatClient, AtatCmd, AtatResp, AtatUrc};
+pub use traits::{AtatClient, AtatCmd, AtatDataClient, AtatDataCmd, AtatResp, AtatUrc};
 pub use urc_matcher::{DefaultUrcMatcher, UrcMatcher, UrcMatcherResult};
 
 /// Commands that can be sent from the client to the ingress manager, for
@@ -268,11 +277,21 @@ pub use urc_matcher::{DefaultUrcMatcher, UrcMatcher, UrcMatcherResult};
 /// the receive buffer on command timeouts.
 #[derive(Debug, Copy, Clone, Hash, PartialEq, Eq)]
 pub enum Command {
-    /// Reset to initial state, including clearing the buffer,
-    /// usually as a result of a command timeout
+    /// Reset to initial state, including clearing the buffer
     Reset,
+    /// Reset to initial state after a command timeout, reporting the
+    /// discarded buffer as [`Event::Timeout`]
+    ///
+    /// [`Event::Timeout`]: observer/enum.Event.html#variant.Timeout
+    Timeout,
     /// Force the ingress manager into receive state
     ForceReceiveState,
+    /// Report [`InternalError::NothingReceived`] if neither the echo nor any
+    /// part of the response to the current command has been received, eg.
+    /// as the device dropped the command while waking up
+    ///
+    /// [`InternalError::NothingReceived`]: enum.InternalError.html#variant.NothingReceived
+    CheckReceived,
 }
 
 /// Configuration of both the ingress manager, and the AT client. Some of these
@@ -284,6 +303,12 @@ pub enum Command {
 pub struct Config {
     mode: Mode,
     cmd_cooldown: u32,
+    silence_timeout: Option<u32>,
+    reset_markers: &'static [&'static [u8]],
+    wake_after_idle: Option<u32>,
+    wake_prefix: &'static [u8],
+    wake_delay: u32,
+    wake_timeout: u32,
 }
 
 impl Default for Config {
@@ -291,6 +316,12 @@ impl Default for Config {
         Self {
             mode: Mode::Blocking,
             cmd_cooldown: 20,
+            silence_timeout: None,
+            reset_markers: &[],
+            wake_after_idle: None,
+            wake_prefix: &[],
+            wake_delay: 0,
+            wake_timeout: 1000,
         }
     }
 }
@@ -309,6 +340,81 @@ impl Config {
         self.cmd_cooldown = ms;
         self
     }
+
+    /// Flush partially received data in the ingress manager after `ms`
+    /// milliseconds without incoming data. See
+    /// [`IngressManager::with_silence_timeout`].
+    ///
+    /// [`IngressManager::with_silence_timeout`]: struct.IngressManager.html#method.with_silence_timeout
+    #[must_use]
+    pub const fn silence_timeout(mut self, ms: u32) -> Self {
+        self.silence_timeout = Some(ms);
+        self
+    }
+
+    /// Lines printed by the device when it restarts, eg. `RDY`. See
+    /// [`IngressManager::with_reset_markers`].
+    ///
+    /// [`IngressManager::with_reset_markers`]: struct.IngressManager.html#method.with_reset_markers
+    #[must_use]
+    pub const fn reset_markers(mut self, markers: &'static [&'static [u8]]) -> Self {
+        self.reset_markers = markers;
+        self
+    }
+
+    /// Wake the device before sending a command, if nothing has been sent or
+    /// received for `ms` milliseconds. This is needed for devices in UART
+    /// power saving modes, eg. u-blox `AT+UPSV` or Quectel `AT+QSCLK`.
+    ///
+    /// Waking asserts the wake signal of the [`Transport`], sends the
+    /// [`wake_prefix`] and waits for the [`wake_delay`]. In
+    /// [`Mode::Timeout`], a command sent after a wakeup is retried once if
+    /// nothing at all is received within the [`wake_timeout`].
+    ///
+    /// [`Transport`]: transport/trait.Transport.html
+    /// [`wake_prefix`]: #method.wake_prefix
+    /// [`wake_delay`]: #method.wake_delay
+    /// [`wake_timeout`]: #method.wake_timeout
+    /// [`Mode::Timeout`]: enum.Mode.html#variant.Timeout
+    #[must_use]
+    pub const fn wake_after_idle(mut self, ms: u32) -> Self {
+        self.wake_after_idle = Some(ms);
+        self
+    }
+
+    /// Bytes sent to wake the device, eg. `b"\r"`
+    #[must_use]
+    pub const fn wake_prefix(mut self, prefix: &'static [u8]) -> Self {
+        self.wake_prefix = prefix;
+        self
+    }
+
+    /// Time in ms to wait after waking the device, before sending the command
+    #[must_use]
+    pub const fn wake_delay(mut self, ms: u32) -> Self {
+        self.wake_delay = ms;
+        self
+    }
+
+    /// Time in ms after which a command sent after a wakeup is retried, in
+    /// [`Mode::Timeout`], if neither its echo nor any part of its response has
+    /// been received. Defaults to 1000 ms.
+    ///
+    /// Once anything is received, the command waits for its full timeout
+    /// instead, and is never sent twice. Devices with echo disabled (`ATE0`)
+    /// send nothing until the response, so slow commands are still retried
+    /// on those.
+    ///
+    /// Relies on the ingress manager handling the check within the wake
+    /// timeout, eg. by digesting from a timer interrupt as well. Otherwise,
+    /// the command also waits for its full timeout.
+    ///
+    /// [`Mode::Timeout`]: enum.Mode.html#variant.Timeout
+    #[must_use]
+    pub const fn wake_timeout(mut self, ms: u32) -> Self {
+        self.wake_timeout = ms;
+        self
+    }
 }
 
 #[cfg(test)]
@@ -0,0 +1,106 @@
+//! Structured events for tracing every AT transaction.
+//!
+//! An [`Observer`] registered with [`ClientBuilder::with_observer`] is called
+//! by both the [`Client`] and the [`IngressManager`], eg. to stream a
+//! transaction log over RTT, or to measure the latency of each command.
+//!
+//! Events from the [`Client`] are timestamped with its clock, while events
+//! from the [`IngressManager`] are timestamped with the latest time passed to
+//! [`write_at`] or [`digest_at`]. Events from an ingress manager only fed
+//! through [`write`] and [`digest`] thus have no timestamp (`at: None`). Use
+//! the same time source for both to compare timestamps.
+//!
+//! Byte slices borrow the buffers of the client or ingress manager, and are
+//! only valid for the duration of the call.
+//!
+//! Example:
+//! ```
+//! use atat::observer::{Event, Observer};
+//!
+//! struct Tracer;
+//!
+//! impl Observer for Tracer {
+//!     fn on_event(&self, event: &Event<'_>) {
+//!         if let Event::Response { at, .. } = event {
+//!             println!("Response received at {:?}", at);
+//!         }
+//!     }
+//! }
+//!
+//! static TRACER: Tracer = Tracer;
+//! ```
+//!
+//! [`ClientBuilder::with_observer`]: ../struct.ClientBuilder.html#method.with_observer
+//! [`Client`]: ../struct.Client.html
+//! [`IngressManager`]: ../struct.IngressManager.html
+//! [`write_at`]: ../struct.IngressManager.html#method.write_at
+//! [`digest_at`]: ../struct.IngressManager.html#method.digest_at
+//! [`write`]: ../struct.IngressManager.html#method.write
+//! [`digest`]: ../struct.IngressManager.html#method.digest
+
+use crate::clock::Instant;
+use crate::error::InternalError;
+
+/// An event in an AT transaction.
+///
+/// `at` is the time of the event, if known. See the [module
+/// documentation](index.html) for when events are timestamped.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Event<'a> {
+    /// Part of a command was written to the serial port. Commands are
+    /// streamed, so a command can be reported in several consecutive chunks,
+    /// all timestamped with the time the command started sending.
+    CommandSent {
+        at: Option<Instant>,
+        bytes: &'a [u8],
+    },
+    /// A chunk of data was received from the serial port, as passed to
+    /// [`IngressManager::write`]
+    ///
+    /// [`IngressManager::write`]: ../struct.IngressManager.html#method.write
+    DataReceived {
+        at: Option<Instant>,
+        bytes: &'a [u8],
+    },
+    /// The echo of a command was received, consuming `bytes`
+    EchoReceived {
+        at: Option<Instant>,
+        bytes: &'a [u8],
+    },
+    /// A response, or error response, was received
+    Response {
+        at: Option<Instant>,
+        result: Result<&'a [u8], &'a InternalError>,
+    },
+    /// A URC was received
+    UrcReceived {
+        at: Option<Instant>,
+        bytes: &'a [u8],
+    },
+    /// The client timed out waiting for a response, discarding the partially
+    /// received `bytes`, if any. Reported by the ingress manager, once it
+    /// handles the timeout.
+    Timeout {
+        at: Option<Instant>,
+        bytes: &'a [u8],
+    },
+    /// The ingress buffer overflowed, discarding `bytes`
+    Overflow {
+        at: Option<Instant>,
+        bytes: &'a [u8],
+    },
+    /// The ingress buffer was cleared, eg. on a timeout, discarding `bytes`
+    BufferCleared {
+        at: Option<Instant>,
+        bytes: &'a [u8],
+    },
+}
+
+/// Observer of AT transactions.
+///
+/// The same observer is called from both the client and the ingress manager,
+/// which usually run in different contexts, eg. a task and the UART
+/// interrupt, so observers must be `Sync`.
+pub trait Observer: Sync {
+    fn on_event(&self, event: &Event<'_>);
+}
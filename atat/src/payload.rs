@@ -0,0 +1,119 @@
+//! Streaming of large payloads past the ingress buffer.
+//!
+//! Responses and URCs carrying a payload, eg. `+USORD: 0,1500,"..."`, would
+//! normally need an ingress buffer, response queue and URC queue large enough
+//! to hold the full payload. Instead, the [`IngressManager`] can be configured
+//! through [`with_payload_sink`] to recognise the header of such payloads, and
+//! forward the payload bytes straight to a [`PayloadSink`] as they are
+//! written, eg. into a ring buffer or socket buffer.
+//!
+//! The payload is removed from the line, leaving only the header fields up
+//! to, and including, the length parameter to be parsed as usual, eg.
+//! `+USORD: 0,1500` followed by `OK`. The payload can follow the length
+//! parameter as a quoted or raw parameter, or, if enabled through
+//! [`PayloadHeader::next_line`], on the line following the header.
+//!
+//! Payloads are streamed as soon as their header is received, so headers
+//! handled by a sink must not also be matched by `#[at_urc(..,
+//! payload_len_field = n)]`.
+//!
+//! Example:
+//! ```
+//! use atat::heapless::spsc::Queue;
+//! use atat::payload::{PayloadHeader, PayloadSink};
+//! use atat::{ComQueue, IngressManager, ResQueue, UrcQueue};
+//!
+//! struct SocketBuffer;
+//!
+//! impl PayloadSink for SocketBuffer {
+//!     fn start(&self, _header: &PayloadHeader, line: &[u8], len: usize) {
+//!         // Parse the socket from the header line, eg. `+USORD: 0,1500`
+//!         println!("{} bytes for {:?}", len, core::str::from_utf8(line));
+//!     }
+//!
+//!     fn write(&self, data: &[u8]) {
+//!         println!("Received {} bytes of socket data", data.len());
+//!     }
+//! }
+//!
+//! static SOCKET_BUFFER: SocketBuffer = SocketBuffer;
+//! static HEADERS: [PayloadHeader; 2] = [
+//!     PayloadHeader::new(b"+USORD:", 1),
+//!     PayloadHeader::new(b"+USORF:", 3),
+//! ];
+//!
+//! static mut RES_QUEUE: ResQueue<64> = Queue::new();
+//! static mut URC_QUEUE: UrcQueue<64, 10> = Queue::new();
+//! static mut COM_QUEUE: ComQueue = Queue::new();
+//!
+//! let (res_p, _res_c) = unsafe { RES_QUEUE.split() };
+//! let (urc_p, _urc_c) = unsafe { URC_QUEUE.split() };
+//! let (_com_p, com_c) = unsafe { COM_QUEUE.split() };
+//!
+//! let mut ingress = IngressManager::new(res_p, urc_p, com_c)
+//!     .with_payload_sink(&HEADERS, &SOCKET_BUFFER);
+//!
+//! // The payload never enters the 64 byte buffer
+//! ingress.write(b"AT+USORD=0,100\r\r\n+USORD: 0,100,\"");
+//! ingress.write(&[b'x'; 100]);
+//! ingress.write(b"\"\r\nOK\r\n");
+//! ingress.digest();
+//! ```
+//!
+//! [`IngressManager`]: ../struct.IngressManager.html
+//! [`with_payload_sink`]: ../struct.IngressManager.html#method.with_payload_sink
+//! [`PayloadHeader::next_line`]: struct.PayloadHeader.html#method.next_line
+
+/// Header of a line carrying a payload
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct PayloadHeader {
+    /// Start of the line, eg. `+USORD:`
+    pub prefix: &'static [u8],
+    /// Index of the parameter holding the length of the payload (zero based)
+    pub len_field: usize,
+    /// Whether the payload can follow on the line after the header, eg.
+    /// `+QIRD: 5\r\nhello`
+    pub next_line: bool,
+}
+
+impl PayloadHeader {
+    #[must_use]
+    pub const fn new(prefix: &'static [u8], len_field: usize) -> Self {
+        Self {
+            prefix,
+            len_field,
+            next_line: false,
+        }
+    }
+
+    /// Accept payloads on the line following the header.
+    ///
+    /// Only enable this for headers that are always followed by a payload,
+    /// as the line after the header is otherwise taken as the payload.
+    #[must_use]
+    pub const fn next_line(mut self) -> Self {
+        self.next_line = true;
+        self
+    }
+}
+
+/// Receiver of payloads streamed by the ingress manager.
+///
+/// The ingress manager usually runs in the UART interrupt, while the payload
+/// is read from another context, so sinks must be `Sync`.
+pub trait PayloadSink: Sync {
+    /// Called before the payload of `len` bytes following the header `line`,
+    /// eg. `+USORD: 0,1500`, matched by `header`. This allows routing the
+    /// payload, eg. by socket.
+    fn start(&self, _header: &PayloadHeader, _line: &[u8], _len: usize) {}
+
+    /// Called with each chunk of a payload, in order, as it is received.
+    /// Payloads can be split in any number of chunks.
+    fn write(&self, data: &[u8]);
+
+    /// Called once all of the payload matched by `header` has been written.
+    ///
+    /// Not called if the payload is abandoned, eg. on a reset of the ingress
+    /// manager.
+    fn end(&self, _header: &PayloadHeader) {}
+}
@@ -0,0 +1,329 @@
+//! Helpers for establishing communication with a device, eg. after power-on.
+//!
+//! [`sync`] sends `AT` until the device responds with `OK`, while
+//! [`autobaud`] additionally probes a list of baud rates through a
+//! [`SetBaud`] implementation. The detected rate can then be locked with
+//! [`SetBaudRate`] (`AT+IPR`). Both are also available as methods of
+//! [`AtatClient`].
+//!
+//! Both helpers rely on commands timing out, and thus require the client to be
+//! in [`Mode::Timeout`]. In other modes, they return `Error::Aborted` without
+//! sending anything.
+//!
+//! Example:
+//! ```ignore
+//! let baud = client.autobaud(&mut uart, &[115_200, 9600], 3)?;
+//! client.send(&atat::sync::SetBaudRate { rate: 115_200 })?;
+//! uart.set_baud(115_200);
+//! ```
+//!
+//! [`AtatClient`]: ../trait.AtatClient.html
+//! [`Mode::Timeout`]: ../enum.Mode.html#variant.Timeout
+
+use core::fmt::Write;
+
+use heapless::Vec;
+
+use crate::atat_log;
+use crate::error::{Error, GenericError, InternalError};
+use crate::traits::{AtatClient, AtatCmd, AtatResp};
+use crate::Mode;
+
+/// Response to commands only responding with `OK`
+#[derive(Debug, Clone, PartialEq)]
+pub struct NoResponse;
+
+impl AtatResp for NoResponse {}
+
+/// Plain `AT` command, used to check that the device is responding
+#[derive(Debug, Clone)]
+pub struct At;
+
+impl AtatCmd<4> for At {
+    type Response = NoResponse;
+    type Error = GenericError;
+
+    const MAX_TIMEOUT_MS: u32 = 200;
+
+    fn as_bytes(&self) -> Vec<u8, 4> {
+        Vec::from_slice(b"AT\r\n").unwrap()
+    }
+
+    fn parse(
+        &self,
+        resp: Result<&[u8], &InternalError>,
+    ) -> Result<Self::Response, Error<Self::Error>> {
+        resp.map(|_| NoResponse).map_err(Error::from)
+    }
+}
+
+/// Set the baud rate of the device (`AT+IPR=<rate>`)
+#[derive(Debug, Clone)]
+pub struct SetBaudRate {
+    pub rate: u32,
+}
+
+impl AtatCmd<19> for SetBaudRate {
+    type Response = NoResponse;
+    type Error = GenericError;
+
+    fn as_bytes(&self) -> Vec<u8, 19> {
+        let mut buf = Vec::new();
+        write!(buf, "AT+IPR={}\r\n", self.rate).unwrap();
+        buf
+    }
+
+    fn parse(
+        &self,
+        resp: Result<&[u8], &InternalError>,
+    ) -> Result<Self::Response, Error<Self::Error>> {
+        resp.map(|_| NoResponse).map_err(Error::from)
+    }
+}
+
+/// Hook to change the baud rate of the serial port, used by [`autobaud`]
+pub trait SetBaud {
+    fn set_baud(&mut self, baud: u32);
+}
+
+fn check_mode<C: AtatClient + ?Sized>(client: &C) -> Result<(), Error> {
+    if client.get_mode() == Mode::Timeout {
+        Ok(())
+    } else {
+        atat_log!(
+            error,
+            "Synchronizing requires the client to be in timeout mode"
+        );
+        Err(Error::Aborted)
+    }
+}
+
+/// Send `AT` until the device responds with `OK`, up to `attempts` times.
+///
+/// Returns `Error::Timeout` if the device did not respond, or
+/// `Error::Aborted` if the client is not in [`Mode::Timeout`], as the attempts
+/// would never time out.
+///
+/// [`Mode::Timeout`]: ../enum.Mode.html#variant.Timeout
+pub fn sync<C: AtatClient + ?Sized>(client: &mut C, attempts: usize) -> Result<(), Error> {
+    check_mode(client)?;
+
+    for attempt in 0..attempts {
+        match nb::block!(client.send(&At)) {
+            Ok(NoResponse) => return Ok(()),
+            Err(_e) => {
+                atat_log!(debug, "Sync attempt {} failed", attempt + 1);
+                // Discard any garbage received while out of sync
+                client.reset();
+            }
+        }
+    }
+    Err(Error::Timeout)
+}
+
+/// Probe each of `rates` with [`sync`], returning the first baud rate at which
+/// the device responds.
+///
+/// The serial port is left configured with the detected rate. Returns
+/// `Error::Timeout` if the device did not respond at any of the rates, or
+/// `Error::Aborted` if the client is not in [`Mode::Timeout`].
+///
+/// [`Mode::Timeout`]: ../enum.Mode.html#variant.Timeout
+pub fn autobaud<C: AtatClient + ?Sized, B: SetBaud + ?Sized>(
+    client: &mut C,
+    port: &mut B,
+    rates: &[u32],
+    attempts: usize,
+) -> Result<u32, Error> {
+    check_mode(client)?;
+
+    for &rate in rates {
+        port.set_baud(rate);
+        client.reset();
+        if sync(client, attempts).is_ok() {
+            atat_log!(info, "Detected baud rate {}", rate);
+            return Ok(rate);
+        }
+    }
+    Err(Error::Timeout)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::clock::{FnClock, Instant};
+    use crate::queues::{ComQueue, ResQueue, UrcQueue};
+    use crate::traits::AtatUrc;
+    use crate::transport::FnTransport;
+    use crate::{Client, Config, IngressManager};
+    use core::cell::{Cell, RefCell};
+    use heapless::spsc::Queue;
+
+    /// Client responding to `AT` once the port is set to `rate`, after
+    /// `fails` timeouts
+    struct ClientMock<'a> {
+        baud: &'a Cell<u32>,
+        rate: u32,
+        fails: usize,
+        sent: usize,
+        resets: usize,
+        mode: Mode,
+    }
+
+    impl AtatClient for ClientMock<'_> {
+        fn send<A: AtatCmd<LEN>, const LEN: usize>(
+            &mut self,
+            cmd: &A,
+        ) -> nb::Result<A::Response, Error<A::Error>> {
+            self.sent += 1;
+            if self.baud.get() == self.rate && self.fails == 0 {
+                cmd.parse(Ok(&[])).map_err(nb::Error::Other)
+            } else {
+                self.fails = self.fails.saturating_sub(1);
+                Err(nb::Error::Other(Error::Timeout))
+            }
+        }
+
+        fn peek_urc_with<URC: AtatUrc, F: FnOnce(URC::Response) -> bool>(&mut self, _f: F) {}
+
+        fn check_response<A: AtatCmd<LEN>, const LEN: usize>(
+            &mut self,
+            _cmd: &A,
+        ) -> nb::Result<A::Response, Error<A::Error>> {
+            Err(nb::Error::WouldBlock)
+        }
+
+        fn get_mode(&self) -> Mode {
+            self.mode
+        }
+
+        fn reset(&mut self) {
+            self.resets += 1;
+        }
+    }
+
+    struct PortMock<'a>(&'a Cell<u32>);
+
+    impl SetBaud for PortMock<'_> {
+        fn set_baud(&mut self, baud: u32) {
+            self.0.set(baud);
+        }
+    }
+
+    fn client(baud: &Cell<u32>, rate: u32, fails: usize) -> ClientMock<'_> {
+        ClientMock {
+            baud,
+            rate,
+            fails,
+            sent: 0,
+            resets: 0,
+            mode: Mode::Timeout,
+        }
+    }
+
+    #[test]
+    fn sync_attempts() {
+        let baud = Cell::new(9600);
+
+        let mut c = client(&baud, 9600, 2);
+        assert_eq!(sync(&mut c, 3), Ok(()));
+        assert_eq!(c.sent, 3);
+        assert_eq!(c.resets, 2);
+
+        let mut c = client(&baud, 9600, 5);
+        assert_eq!(sync(&mut c, 3), Err(Error::Timeout));
+        assert_eq!(c.sent, 3);
+    }
+
+    #[test]
+    fn autobaud_rates() {
+        let baud = Cell::new(0);
+        let mut port = PortMock(&baud);
+
+        let mut c = client(&baud, 57_600, 0);
+        assert_eq!(
+            autobaud(&mut c, &mut port, &[9600, 57_600, 115_200], 2),
+            Ok(57_600)
+        );
+        assert_eq!(baud.get(), 57_600);
+        assert_eq!(c.sent, 3);
+
+        let mut c = client(&baud, 230_400, 0);
+        assert_eq!(
+            autobaud(&mut c, &mut port, &[9600, 57_600, 115_200], 2),
+            Err(Error::Timeout)
+        );
+        assert_eq!(c.sent, 6);
+    }
+
+    #[test]
+    fn requires_timeout_mode() {
+        let baud = Cell::new(9600);
+        let mut port = PortMock(&baud);
+
+        let mut c = client(&baud, 9600, 0);
+        c.mode = Mode::Blocking;
+        assert_eq!(c.sync(3), Err(Error::Aborted));
+        assert_eq!(c.autobaud(&mut port, &[9600], 3), Err(Error::Aborted));
+        assert_eq!(c.sent, 0);
+    }
+
+    #[test]
+    fn autobaud_client() {
+        static mut RES_Q: ResQueue<64> = Queue::new();
+        static mut URC_Q: UrcQueue<64, 4> = Queue::new();
+        static mut COM_Q: ComQueue = Queue::new();
+        let (res_p, res_c) = unsafe { RES_Q.split() };
+        let (urc_p, urc_c) = unsafe { URC_Q.split() };
+        let (com_p, com_c) = unsafe { COM_Q.split() };
+
+        let ingress = RefCell::new(IngressManager::new(res_p, urc_p, com_c));
+        let baud = Cell::new(0);
+        let sent = Cell::new(0);
+
+        // Device echoing and answering commands at 57600 baud only
+        let tx = FnTransport::new(|buf: &[u8]| -> Result<(), ()> {
+            let mut ingress = ingress.borrow_mut();
+            // Handle any reset from the client first
+            ingress.digest();
+            if baud.get() == 57_600 {
+                ingress.write(buf);
+                ingress.write(b"\r\nOK\r\n");
+            } else {
+                ingress.write(b"\xf0\x0f");
+            }
+            ingress.digest();
+            sent.set(sent.get() + 1);
+            Ok(())
+        });
+
+        let mut ms = 0;
+        let clock = FnClock::new(|| {
+            ms += 10;
+            Instant::from_millis(ms)
+        });
+
+        let mut client: Client<_, _, 64, 4> =
+            Client::new(tx, res_c, urc_c, com_p, clock, Config::new(Mode::Timeout));
+        let mut port = PortMock(&baud);
+
+        assert_eq!(
+            client.autobaud(&mut port, &[9600, 57_600, 115_200], 2),
+            Ok(57_600)
+        );
+        assert_eq!(baud.get(), 57_600);
+        assert_eq!(sent.get(), 3);
+
+        // Communication is established
+        assert_eq!(client.send(&At), Ok(NoResponse));
+    }
+
+    #[test]
+    fn set_baud_rate() {
+        assert_eq!(
+            &SetBaudRate { rate: 115_200 }.as_bytes()[..],
+            b"AT+IPR=115200\r\n"
+        );
+        assert_eq!(&At.as_bytes()[..], b"AT\r\n");
+    }
+}
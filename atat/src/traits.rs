@@ -1,3 +1,4 @@
+use crate::sync::SetBaud;
 use crate::Mode;
 use crate::{
     error::{Error, InternalError},
@@ -18,7 +19,36 @@ use heapless::{String, Vec};
 ///
 /// impl AtatResp for GreetingText {}
 /// ```
-pub trait AtatResp {}
+pub trait AtatResp {
+    /// The maximum length of the response parameters in bytes, or `None` if
+    /// unknown (default).
+    ///
+    /// When derived with `#[at_resp(max_len)]`, this is calculated from the
+    /// `AtatLen` implementations of the fields, or their `#[at_arg(len = ..)]`
+    /// attributes. It is used to check that responses fit in the ingress
+    /// buffer (`BUF_LEN`), through [`AtatCmd::MAX_RESPONSE_LEN`].
+    ///
+    /// [`AtatCmd::MAX_RESPONSE_LEN`]: trait.AtatCmd.html#associatedconstant.MAX_RESPONSE_LEN
+    const MAX_LEN: Option<usize> = None;
+}
+
+macro_rules! impl_tuple_resp {
+    ($($ty:ident),+) => {
+        /// Tuples can be used as response types directly, eg.
+        /// `type Response = (u8, String<16>)`. Their `MAX_LEN` is unknown, as
+        /// the elements need not implement `AtatLen`.
+        impl<$($ty),+> AtatResp for ($($ty,)+) {}
+    };
+}
+
+impl_tuple_resp!(A);
+impl_tuple_resp!(A, B);
+impl_tuple_resp!(A, B, C);
+impl_tuple_resp!(A, B, C, D);
+impl_tuple_resp!(A, B, C, D, E);
+impl_tuple_resp!(A, B, C, D, E, F);
+impl_tuple_resp!(A, B, C, D, E, F, G);
+impl_tuple_resp!(A, B, C, D, E, F, G, H);
 
 pub trait AtatUrc {
     /// The type of the response. Usually the enum this trait is implemented on.
@@ -85,9 +115,74 @@ pub trait AtatCmd<const LEN: usize> {
     /// Implemented to enhance expandability fo ATAT
     const EXPECTS_RESPONSE_CODE: bool = true;
 
+    /// The maximum length of the full response to the command in bytes,
+    /// including the response prefix, eg. `+CMD: `, and line terminators, or
+    /// `None` if unknown.
+    ///
+    /// Debug builds of the client check this against the ingress buffer
+    /// (`BUF_LEN`). Defaults to [`AtatResp::MAX_LEN`], with the length of the
+    /// command as an upper bound of the response prefix. Derived commands use
+    /// the exact prefix.
+    ///
+    /// [`AtatResp::MAX_LEN`]: trait.AtatResp.html#associatedconstant.MAX_LEN
+    const MAX_RESPONSE_LEN: Option<usize> = match <Self::Response as AtatResp>::MAX_LEN {
+        // Leading and trailing line endings, and `\r\nOK\r\n`
+        Some(len) => Some(LEN + len + 10),
+        None => None,
+    };
+
     /// Return the command as a heapless `Vec` of bytes.
+    ///
+    /// Implementations are allowed to panic if the command cannot be
+    /// serialized. Use [`try_as_bytes`] for a fallible alternative.
+    ///
+    /// [`try_as_bytes`]: #method.try_as_bytes
     fn as_bytes(&self) -> Vec<u8, LEN>;
 
+    /// Return the command as a heapless `Vec` of bytes, or
+    /// `Error::Serialize` if the command cannot be serialized (eg. because
+    /// the serialized command does not fit in `LEN` bytes).
+    ///
+    /// Defaults to wrapping [`as_bytes`].
+    ///
+    /// [`as_bytes`]: #tymethod.as_bytes
+    fn try_as_bytes(&self) -> Result<Vec<u8, LEN>, Error<Self::Error>> {
+        Ok(self.as_bytes())
+    }
+
+    /// Serialize the command into `buf`, returning the number of bytes
+    /// written, or `Error::Serialize` if the command does not fit in `buf`.
+    ///
+    /// Defaults to copying the result of [`try_as_bytes`] into `buf`.
+    ///
+    /// [`try_as_bytes`]: #method.try_as_bytes
+    fn write(&self, buf: &mut [u8]) -> Result<usize, Error<Self::Error>> {
+        let bytes = self.try_as_bytes()?;
+        buf.get_mut(..bytes.len())
+            .ok_or(Error::Serialize)?
+            .copy_from_slice(&bytes);
+        Ok(bytes.len())
+    }
+
+    /// Serialize the command in one or more chunks, handing each chunk to
+    /// `write` as soon as it is produced. This allows the client to write
+    /// commands straight to the serial port, without staging them in a
+    /// buffer.
+    ///
+    /// Commands not fitting in `LEN` bytes must fail with `Error::Serialize`
+    /// before any chunk is written. Errors returned by `write` abort the
+    /// command, possibly after some chunks have been written.
+    ///
+    /// Defaults to passing the result of [`try_as_bytes`] as a single chunk.
+    ///
+    /// [`try_as_bytes`]: #method.try_as_bytes
+    fn write_to<F>(&self, mut write: F) -> Result<(), Error<Self::Error>>
+    where
+        F: FnMut(&[u8]) -> Result<(), Error<Self::Error>>,
+    {
+        write(&self.try_as_bytes()?)
+    }
+
     /// Parse the response into a `Self::Response` or `Error<Self::Error>` instance.
     fn parse(
         &self,
@@ -95,6 +190,66 @@ pub trait AtatCmd<const LEN: usize> {
     ) -> Result<Self::Response, Error<Self::Error>>;
 }
 
+/// Commands with a data phase, eg. `AT+CMGS` or `AT+USOWR`, where the device
+/// responds to the command with a `>` or `@` prompt, and then takes a raw
+/// payload before sending the final response.
+///
+/// These are sent in a single transaction through
+/// [`AtatDataClient::send_with_data`]. The command itself only serializes the
+/// header, eg. `AT+USOWR=0,5`, while the payload is returned by [`payload`].
+///
+/// As the payload is not preceded by an `AT` echo, the final response is
+/// received in forced receive state. Devices echoing the payload, eg. SMS
+/// text mode with `ATE1`, will thus include the echo in the response.
+///
+/// Example:
+/// ```
+/// use atat::{AtatCmd, AtatDataCmd, AtatResp, Error, InternalError, GenericError};
+/// use core::fmt::Write;
+/// use heapless::Vec;
+///
+/// pub struct WriteSocketData<'a> {
+///     pub socket: u8,
+///     pub data: &'a [u8],
+/// }
+///
+/// pub struct NoResponse;
+///
+/// impl AtatResp for NoResponse {};
+///
+/// impl<'a> AtatCmd<24> for WriteSocketData<'a> {
+///     type Response = NoResponse;
+///     type Error = GenericError;
+///
+///     fn as_bytes(&self) -> Vec<u8, 24> {
+///         let mut buf: Vec<u8, 24> = Vec::new();
+///         write!(buf, "AT+USOWR={},{}\r\n", self.socket, self.data.len());
+///         buf
+///     }
+///
+///     fn parse(&self, resp: Result<&[u8], &InternalError>) -> Result<Self::Response, Error<Self::Error>> {
+///         Ok(NoResponse)
+///     }
+/// }
+///
+/// impl<'a> AtatDataCmd<24> for WriteSocketData<'a> {
+///     fn payload(&self) -> &[u8] {
+///         self.data
+///     }
+/// }
+/// ```
+///
+/// [`AtatDataClient::send_with_data`]: trait.AtatDataClient.html#tymethod.send_with_data
+/// [`payload`]: #tymethod.payload
+pub trait AtatDataCmd<const LEN: usize>: AtatCmd<LEN> {
+    /// Byte sent after the payload, eg. Ctrl-Z (`0x1A`) for `AT+CMGS`, or
+    /// `None` for commands taking a fixed length payload.
+    const PAYLOAD_TERMINATOR: Option<u8> = None;
+
+    /// The raw payload, sent once the prompt has been received
+    fn payload(&self) -> &[u8];
+}
+
 pub trait AtatClient {
     /// Send an AT command.
     ///
@@ -108,6 +263,11 @@ pub trait AtatClient {
     /// This function will also make sure that atleast `self.config.cmd_cooldown`
     /// has passed since the last response or URC has been received, to allow
     /// the slave AT device time to deliver URC's.
+    ///
+    /// Commands that cannot be serialized return `Error::Serialize` without
+    /// sending anything. If writing to the serial port fails part way through
+    /// the command, `Error::Write` is returned, and the ingress manager is
+    /// reset to discard the echo of the partial command.
     fn send<A: AtatCmd<LEN>, const LEN: usize>(
         &mut self,
         cmd: &A,
@@ -174,6 +334,54 @@ pub trait AtatClient {
 
     /// Reset the client, queues and ingress buffer, discarding any contents
     fn reset(&mut self);
+
+    /// Send `AT` until the device responds with `OK`, up to `attempts` times.
+    /// See [`sync::sync`].
+    ///
+    /// [`sync::sync`]: sync/fn.sync.html
+    fn sync(&mut self, attempts: usize) -> Result<(), Error> {
+        crate::sync::sync(self, attempts)
+    }
+
+    /// Probe each of `rates` through `port`, returning the first baud rate at
+    /// which the device responds. See [`sync::autobaud`].
+    ///
+    /// [`sync::autobaud`]: sync/fn.autobaud.html
+    fn autobaud<B: SetBaud>(
+        &mut self,
+        port: &mut B,
+        rates: &[u32],
+        attempts: usize,
+    ) -> Result<u32, Error> {
+        crate::sync::autobaud(self, port, rates, attempts)
+    }
+}
+
+/// Extension of [`AtatClient`] for commands with a data phase.
+///
+/// [`AtatClient`]: trait.AtatClient.html
+pub trait AtatDataClient: AtatClient {
+    /// Send an AT command with a data phase, eg. `AT+CMGS` or `AT+USOWR`.
+    ///
+    /// `cmd` must implement [`AtatDataCmd`].
+    ///
+    /// The command is sent, followed by its payload once the device prompts
+    /// for it, and the final response is returned. [`AtatCmd::MAX_TIMEOUT_MS`]
+    /// applies to both waiting for the prompt, and waiting for the final
+    /// response. The payload is only sent once the prompt is received. Error
+    /// responses received instead are returned as for [`send`], while any
+    /// other response, eg. a bare `OK`, fails with `Error::InvalidResponse`.
+    ///
+    /// Blocks as [`send`], except that commands are never retried after a
+    /// wakeup, as the device might still be waiting for the payload.
+    ///
+    /// [`AtatDataCmd`]: trait.AtatDataCmd.html
+    /// [`AtatCmd::MAX_TIMEOUT_MS`]: trait.AtatCmd.html#associatedconstant.MAX_TIMEOUT_MS
+    /// [`send`]: trait.AtatClient.html#tymethod.send
+    fn send_with_data<A: AtatDataCmd<LEN>, const LEN: usize>(
+        &mut self,
+        cmd: &A,
+    ) -> nb::Result<A::Response, Error<A::Error>>;
 }
 
 impl<T, const L: usize> AtatResp for Vec<T, L> where T: AtatResp {}
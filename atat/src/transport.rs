@@ -0,0 +1,439 @@
+//! Abstraction over the serial port used by the [`Client`] to send commands.
+//!
+//! [`Transport`] is implemented for every `embedded_hal`
+//! [`serial::Write<u8>`][serialwrite], and adapters are provided for other
+//! serial traits:
+//!
+//! - `IoTransport` for `embedded_io::Write` (feature `embedded-io`)
+//! - `AsyncIoTransport` for `embedded_io_async::Write` (feature
+//!   `embedded-io-async`)
+//! - `Hal02Transport` for `embedded_hal` 0.2 `serial::Write<u8>` (feature
+//!   `embedded-hal-02`)
+//! - [`FnTransport`] for a plain `FnMut(&[u8])` closure
+//!
+//! Devices with a wake pin, eg. DTR, can be handled by wrapping the transport
+//! in a [`WakePinTransport`].
+//!
+//! As the [`Client`] is blocking, `AsyncIoTransport` busy-polls the writes
+//! of the async writer to completion. To drive them from an executor instead,
+//! wrap its `block_on` in an [`FnTransport`], eg. `FnTransport::new(|buf:
+//! &[u8]| block_on(tx.write_all(buf)))`.
+//!
+//! [`Client`]: ../struct.Client.html
+//! [serialwrite]: ../../embedded_hal/serial/trait.Write.html
+
+use embedded_hal::serial;
+
+/// A blocking, slice based serial writer.
+pub trait Transport {
+    type Error;
+
+    /// Write all of `buf`, blocking until it has been accepted by the
+    /// underlying writer.
+    fn write_all(&mut self, buf: &[u8]) -> Result<(), Self::Error>;
+
+    /// Block until all written data has been transmitted.
+    fn flush(&mut self) -> Result<(), Self::Error>;
+
+    /// Assert any wake signal of the device, before sending a command on an
+    /// idle link. See [`Config::wake_after_idle`].
+    ///
+    /// Defaults to doing nothing.
+    ///
+    /// [`Config::wake_after_idle`]: ../struct.Config.html#method.wake_after_idle
+    fn wake(&mut self) -> Result<(), Self::Error> {
+        Ok(())
+    }
+}
+
+impl<W> Transport for W
+where
+    W: serial::Write<u8>,
+{
+    type Error = W::Error;
+
+    fn write_all(&mut self, buf: &[u8]) -> Result<(), Self::Error> {
+        for c in buf {
+            nb::block!(self.try_write(*c))?;
+        }
+        Ok(())
+    }
+
+    fn flush(&mut self) -> Result<(), Self::Error> {
+        nb::block!(self.try_flush())
+    }
+}
+
+/// [`Transport`] adapter for a closure writing a slice of bytes.
+///
+/// Example:
+/// ```
+/// use atat::transport::{FnTransport, Transport};
+///
+/// let mut written = heapless::Vec::<u8, 16>::new();
+/// let mut tx = FnTransport::new(|buf: &[u8]| written.extend_from_slice(buf));
+/// tx.write_all(b"AT\r\n").unwrap();
+/// drop(tx);
+///
+/// assert_eq!(&written[..], b"AT\r\n");
+/// ```
+pub struct FnTransport<F>(F);
+
+impl<F, E> FnTransport<F>
+where
+    F: FnMut(&[u8]) -> Result<(), E>,
+{
+    pub const fn new(f: F) -> Self {
+        Self(f)
+    }
+}
+
+impl<F, E> Transport for FnTransport<F>
+where
+    F: FnMut(&[u8]) -> Result<(), E>,
+{
+    type Error = E;
+
+    fn write_all(&mut self, buf: &[u8]) -> Result<(), Self::Error> {
+        (self.0)(buf)
+    }
+
+    fn flush(&mut self) -> Result<(), Self::Error> {
+        Ok(())
+    }
+}
+
+/// A pin used to wake the device, eg. DTR.
+pub trait WakePin {
+    /// Wake the device, eg. by pulsing or asserting the pin
+    fn wake(&mut self);
+}
+
+/// [`Transport`] adapter waking the device through a [`WakePin`].
+pub struct WakePinTransport<T, P> {
+    pub transport: T,
+    pub pin: P,
+}
+
+impl<T, P> WakePinTransport<T, P>
+where
+    T: Transport,
+    P: WakePin,
+{
+    pub const fn new(transport: T, pin: P) -> Self {
+        Self { transport, pin }
+    }
+}
+
+impl<T, P> Transport for WakePinTransport<T, P>
+where
+    T: Transport,
+    P: WakePin,
+{
+    type Error = T::Error;
+
+    fn write_all(&mut self, buf: &[u8]) -> Result<(), Self::Error> {
+        self.transport.write_all(buf)
+    }
+
+    fn flush(&mut self) -> Result<(), Self::Error> {
+        self.transport.flush()
+    }
+
+    fn wake(&mut self) -> Result<(), Self::Error> {
+        self.pin.wake();
+        self.transport.wake()
+    }
+}
+
+/// [`Transport`] adapter for an `embedded_io::Write` implementation.
+#[cfg(feature = "embedded-io")]
+pub struct IoTransport<W>(pub W);
+
+#[cfg(feature = "embedded-io")]
+impl<W> Transport for IoTransport<W>
+where
+    W: embedded_io::Write,
+{
+    type Error = W::Error;
+
+    fn write_all(&mut self, buf: &[u8]) -> Result<(), Self::Error> {
+        self.0.write_all(buf)
+    }
+
+    fn flush(&mut self) -> Result<(), Self::Error> {
+        self.0.flush()
+    }
+}
+
+/// [`Transport`] adapter for an `embedded_hal` 0.2 `serial::Write<u8>`
+/// implementation.
+#[cfg(feature = "embedded-hal-02")]
+pub struct Hal02Transport<W>(pub W);
+
+#[cfg(feature = "embedded-hal-02")]
+impl<W> Transport for Hal02Transport<W>
+where
+    W: embedded_hal_02::serial::Write<u8>,
+{
+    type Error = W::Error;
+
+    fn write_all(&mut self, buf: &[u8]) -> Result<(), Self::Error> {
+        for c in buf {
+            nb::block!(self.0.write(*c))?;
+        }
+        Ok(())
+    }
+
+    fn flush(&mut self) -> Result<(), Self::Error> {
+        nb::block!(self.0.flush())
+    }
+}
+
+/// [`Transport`] adapter for an `embedded_io_async::Write` implementation.
+///
+/// Each write is busy-polled to completion, without waiting for the writer
+/// to wake the task, so the writer must make progress when polled.
+#[cfg(feature = "embedded-io-async")]
+pub struct AsyncIoTransport<W>(pub W);
+
+#[cfg(feature = "embedded-io-async")]
+impl<W> Transport for AsyncIoTransport<W>
+where
+    W: embedded_io_async::Write,
+{
+    type Error = W::Error;
+
+    fn write_all(&mut self, buf: &[u8]) -> Result<(), Self::Error> {
+        block_on(self.0.write_all(buf))
+    }
+
+    fn flush(&mut self) -> Result<(), Self::Error> {
+        block_on(self.0.flush())
+    }
+}
+
+/// Poll `future` until it completes, using a waker doing nothing
+#[cfg(feature = "embedded-io-async")]
+fn block_on<F: core::future::Future>(future: F) -> F::Output {
+    use core::task::{Context, Poll, RawWaker, RawWakerVTable, Waker};
+
+    fn noop_raw_waker() -> RawWaker {
+        fn clone(_: *const ()) -> RawWaker {
+            noop_raw_waker()
+        }
+        fn noop(_: *const ()) {}
+        static VTABLE: RawWakerVTable = RawWakerVTable::new(clone, noop, noop, noop);
+        RawWaker::new(core::ptr::null(), &VTABLE)
+    }
+
+    // Safety: the vtable functions do nothing, so the contract of `RawWaker`
+    // is trivially upheld
+    let waker = unsafe { Waker::from_raw(noop_raw_waker()) };
+    let mut cx = Context::from_waker(&waker);
+    let mut future = core::pin::pin!(future);
+    loop {
+        if let Poll::Ready(output) = future.as_mut().poll(&mut cx) {
+            return output;
+        }
+        core::hint::spin_loop();
+    }
+}
+
+#[cfg(all(
+    test,
+    any(
+        feature = "embedded-io",
+        feature = "embedded-io-async",
+        feature = "embedded-hal-02"
+    )
+))]
+mod test {
+    use super::*;
+
+    #[cfg(feature = "embedded-io")]
+    mod io {
+        use super::*;
+        use embedded_io::ErrorKind;
+
+        /// Writer accepting at most two bytes per call, failing once `fail_at`
+        /// bytes have been written
+        struct ShortWriter {
+            written: std::vec::Vec<u8>,
+            fail_at: usize,
+            flushed: bool,
+        }
+
+        impl ShortWriter {
+            fn new(fail_at: usize) -> Self {
+                Self {
+                    written: std::vec::Vec::new(),
+                    fail_at,
+                    flushed: false,
+                }
+            }
+        }
+
+        impl embedded_io::ErrorType for ShortWriter {
+            type Error = ErrorKind;
+        }
+
+        impl embedded_io::Write for ShortWriter {
+            fn write(&mut self, buf: &[u8]) -> Result<usize, Self::Error> {
+                if self.written.len() >= self.fail_at {
+                    return Err(ErrorKind::BrokenPipe);
+                }
+                let n = buf.len().min(2);
+                self.written.extend_from_slice(&buf[..n]);
+                Ok(n)
+            }
+
+            fn flush(&mut self) -> Result<(), Self::Error> {
+                self.flushed = true;
+                Ok(())
+            }
+        }
+
+        #[test]
+        fn io_transport_retries_short_writes() {
+            let mut tx = IoTransport(ShortWriter::new(usize::MAX));
+            tx.write_all(b"AT+CGMI\r\n").unwrap();
+            tx.flush().unwrap();
+            assert_eq!(&tx.0.written[..], b"AT+CGMI\r\n");
+            assert!(tx.0.flushed);
+        }
+
+        #[test]
+        fn io_transport_propagates_errors() {
+            let mut tx = IoTransport(ShortWriter::new(4));
+            assert_eq!(tx.write_all(b"AT+CGMI\r\n"), Err(ErrorKind::BrokenPipe));
+            assert_eq!(&tx.0.written[..], b"AT+C");
+        }
+    }
+
+    #[cfg(feature = "embedded-io-async")]
+    mod io_async {
+        use super::*;
+        use core::task::Poll;
+        use embedded_io_async::ErrorKind;
+
+        /// Async writer accepting at most two bytes per call, pending once
+        /// before each write and failing once `fail_at` bytes have been written
+        struct PendingWriter {
+            written: std::vec::Vec<u8>,
+            fail_at: usize,
+        }
+
+        impl embedded_io_async::ErrorType for PendingWriter {
+            type Error = ErrorKind;
+        }
+
+        impl embedded_io_async::Write for PendingWriter {
+            async fn write(&mut self, buf: &[u8]) -> Result<usize, Self::Error> {
+                let mut pending = true;
+                core::future::poll_fn(|_| {
+                    if core::mem::take(&mut pending) {
+                        Poll::Pending
+                    } else {
+                        Poll::Ready(())
+                    }
+                })
+                .await;
+
+                if self.written.len() >= self.fail_at {
+                    return Err(ErrorKind::BrokenPipe);
+                }
+                let n = buf.len().min(2);
+                self.written.extend_from_slice(&buf[..n]);
+                Ok(n)
+            }
+        }
+
+        #[test]
+        fn async_io_transport_polls_to_completion() {
+            let mut tx = AsyncIoTransport(PendingWriter {
+                written: std::vec::Vec::new(),
+                fail_at: usize::MAX,
+            });
+            tx.write_all(b"AT+CGMI\r\n").unwrap();
+            tx.flush().unwrap();
+            assert_eq!(&tx.0.written[..], b"AT+CGMI\r\n");
+        }
+
+        #[test]
+        fn async_io_transport_propagates_errors() {
+            let mut tx = AsyncIoTransport(PendingWriter {
+                written: std::vec::Vec::new(),
+                fail_at: 4,
+            });
+            assert_eq!(tx.write_all(b"AT+CGMI\r\n"), Err(ErrorKind::BrokenPipe));
+            assert_eq!(&tx.0.written[..], b"AT+C");
+        }
+    }
+
+    #[cfg(feature = "embedded-hal-02")]
+    mod hal02 {
+        use super::*;
+
+        /// Serial port busy on every other byte, failing once `fail_at` bytes
+        /// have been written
+        struct BusySerial {
+            written: std::vec::Vec<u8>,
+            fail_at: usize,
+            busy: bool,
+            flushed: bool,
+        }
+
+        impl BusySerial {
+            fn new(fail_at: usize) -> Self {
+                Self {
+                    written: std::vec::Vec::new(),
+                    fail_at,
+                    busy: false,
+                    flushed: false,
+                }
+            }
+        }
+
+        impl embedded_hal_02::serial::Write<u8> for BusySerial {
+            type Error = ();
+
+            fn write(&mut self, word: u8) -> nb::Result<(), Self::Error> {
+                self.busy = !self.busy;
+                if self.busy {
+                    return Err(nb::Error::WouldBlock);
+                }
+                if self.written.len() >= self.fail_at {
+                    return Err(nb::Error::Other(()));
+                }
+                self.written.push(word);
+                Ok(())
+            }
+
+            fn flush(&mut self) -> nb::Result<(), Self::Error> {
+                self.busy = !self.busy;
+                if self.busy {
+                    return Err(nb::Error::WouldBlock);
+                }
+                self.flushed = true;
+                Ok(())
+            }
+        }
+
+        #[test]
+        fn hal02_transport_retries_busy_writes() {
+            let mut tx = Hal02Transport(BusySerial::new(usize::MAX));
+            tx.write_all(b"AT+CGMI\r\n").unwrap();
+            tx.flush().unwrap();
+            assert_eq!(&tx.0.written[..], b"AT+CGMI\r\n");
+            assert!(tx.0.flushed);
+        }
+
+        #[test]
+        fn hal02_transport_propagates_errors() {
+            let mut tx = Hal02Transport(BusySerial::new(4));
+            assert_eq!(tx.write_all(b"AT+CGMI\r\n"), Err(()));
+            assert_eq!(&tx.0.written[..], b"AT+C");
+        }
+    }
+}
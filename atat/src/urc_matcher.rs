@@ -1,5 +1,7 @@
 use heapless::Vec;
 
+use crate::helpers::SliceExt;
+
 /// A user-defined URC matcher
 ///
 /// This is used to detect and consume URCs that are not terminated with
@@ -74,3 +76,324 @@ impl UrcMatcher for DefaultUrcMatcher {
         UrcMatcherResult::NotHandled
     }
 }
+
+macro_rules! impl_tuple_matcher {
+    ($($ty:ident $idx:tt),+) => {
+        /// Tuples of matchers can be used to combine several independent
+        /// matchers, eg. `(SocketUrcMatcher, GnssUrcMatcher)`.
+        ///
+        /// The matchers are tried in order, and the first [`Complete`] result
+        /// is returned. Otherwise the result is [`Incomplete`] if any of the
+        /// matchers are waiting for more data, and [`NotHandled`] if none of
+        /// them are.
+        ///
+        /// [`Complete`]: enum.UrcMatcherResult.html#variant.Complete
+        /// [`Incomplete`]: enum.UrcMatcherResult.html#variant.Incomplete
+        /// [`NotHandled`]: enum.UrcMatcherResult.html#variant.NotHandled
+        impl<$($ty: UrcMatcher),+> UrcMatcher for ($($ty,)+) {
+            fn process<const L: usize>(&mut self, buf: &mut Vec<u8, L>) -> UrcMatcherResult<L> {
+                let mut result = UrcMatcherResult::NotHandled;
+                $(
+                    match self.$idx.process(buf) {
+                        UrcMatcherResult::Complete(urc) => return UrcMatcherResult::Complete(urc),
+                        UrcMatcherResult::Incomplete => result = UrcMatcherResult::Incomplete,
+                        UrcMatcherResult::NotHandled => {}
+                    }
+                )+
+                result
+            }
+        }
+    };
+}
+
+impl_tuple_matcher!(A 0);
+impl_tuple_matcher!(A 0, B 1);
+impl_tuple_matcher!(A 0, B 1, C 2);
+impl_tuple_matcher!(A 0, B 1, C 2, D 3);
+impl_tuple_matcher!(A 0, B 1, C 2, D 3, E 4);
+impl_tuple_matcher!(A 0, B 1, C 2, D 3, E 4, F 5);
+impl_tuple_matcher!(A 0, B 1, C 2, D 3, E 4, F 5, G 6);
+impl_tuple_matcher!(A 0, B 1, C 2, D 3, E 4, F 5, G 6, H 7);
+
+/// Match a URC spanning `lines` lines, eg. a header line followed by a line
+/// of data. Used by matchers generated with `#[at_urc(.., lines = n)]`.
+///
+/// URCs spanning zero lines are never matched.
+pub fn match_lines<const L: usize>(buf: &mut Vec<u8, L>, lines: usize) -> UrcMatcherResult<L> {
+    if lines == 0 {
+        return UrcMatcherResult::NotHandled;
+    }
+
+    let mut end = 0;
+    for _ in 0..lines {
+        match buf[end..].windows(2).position(|w| w == b"\r\n") {
+            Some(i) => end += i + 2,
+            None => return UrcMatcherResult::Incomplete,
+        }
+    }
+    take_urc(buf, end - 2, end)
+}
+
+/// Match a URC carrying a payload, with the length of the payload given by
+/// the parameter at index `field` (zero based), eg. `+USORF: 0,"1.2.3.4",
+/// 5000,4,"data"` with `field = 3`. The payload can either follow the length
+/// parameter as a quoted or raw parameter, or on the next line. Used by
+/// matchers generated with `#[at_urc(.., payload_len_field = n)]`.
+pub fn match_payload_len<const L: usize>(
+    buf: &mut Vec<u8, L>,
+    field: usize,
+) -> UrcMatcherResult<L> {
+    let PayloadPosition {
+        start: payload_start,
+        len,
+        quoted,
+        ..
+    } = match find_payload(buf, field, true) {
+        PayloadSearch::Found(position) => position,
+        PayloadSearch::Incomplete => return UrcMatcherResult::Incomplete,
+        PayloadSearch::NotHandled => return UrcMatcherResult::NotHandled,
+    };
+
+    // A payload not fitting the buffer along with its line ending can never
+    // complete, so leave it to be dropped instead of waiting forever
+    let data_end = match payload_start
+        .checked_add(len)
+        .and_then(|end| end.checked_add(quoted as usize))
+    {
+        Some(data_end) if data_end < L => data_end,
+        _ => return UrcMatcherResult::NotHandled,
+    };
+    if buf.len() < data_end {
+        return UrcMatcherResult::Incomplete;
+    }
+    if quoted && buf[data_end - 1] != b'"' {
+        return UrcMatcherResult::NotHandled;
+    }
+
+    // Consume the line ending following the payload
+    match &buf[data_end..] {
+        [] | [b'\r'] => UrcMatcherResult::Incomplete,
+        [b'\r', b'\n', ..] => take_urc(buf, data_end, data_end + 2),
+        _ => take_urc(buf, data_end, data_end),
+    }
+}
+
+/// Position of a payload within a line, following its length parameter
+pub(crate) struct PayloadPosition {
+    /// Index just past the length parameter
+    pub len_end: usize,
+    /// Index of the first byte of the payload
+    pub start: usize,
+    /// Length of the payload
+    pub len: usize,
+    /// Whether the payload is enclosed in quotes
+    pub quoted: bool,
+}
+
+pub(crate) enum PayloadSearch {
+    NotHandled,
+    Incomplete,
+    Found(PayloadPosition),
+}
+
+/// Find the payload in a line starting with a header like `+USORF: ..`, with
+/// the length of the payload given by the parameter at index `field`. A
+/// payload on the line following the header is only accepted if `next_line`
+/// is set.
+pub(crate) fn find_payload(buf: &[u8], field: usize, next_line: bool) -> PayloadSearch {
+    let header_end = buf.windows(2).position(|w| w == b"\r\n");
+    let params_start = match buf.iter().position(|&c| c == b':') {
+        Some(i) if header_end.is_none() || header_end > Some(i) => i + 1,
+        _ if header_end.is_some() => return PayloadSearch::NotHandled,
+        _ => return PayloadSearch::Incomplete,
+    };
+
+    // Find the length parameter, skipping over quoted strings
+    let mut index = params_start;
+    let mut len_start = params_start;
+    let mut current_field = 0;
+    let mut in_quotes = false;
+    loop {
+        match buf.get(index) {
+            None => return PayloadSearch::Incomplete,
+            Some(b'"') => in_quotes = !in_quotes,
+            Some(&c) if !in_quotes && (c == b',' || c == b'\r') && current_field == field => break,
+            Some(b',') if !in_quotes => {
+                current_field += 1;
+                len_start = index + 1;
+            }
+            Some(b'\r') if !in_quotes => return PayloadSearch::NotHandled,
+            _ => {}
+        }
+        index += 1;
+    }
+
+    let len: usize = match core::str::from_utf8(buf[len_start..index].trim(b" "))
+        .ok()
+        .and_then(|s| s.parse().ok())
+    {
+        Some(len) => len,
+        None => return PayloadSearch::NotHandled,
+    };
+
+    let (start, quoted) = match buf.get(index..index + 2) {
+        Some(b",\"") => (index + 2, true),
+        Some(b"\r\n") if next_line => (index + 2, false),
+        Some(&[b',', _]) => (index + 1, false),
+        Some(_) => return PayloadSearch::NotHandled,
+        None => return PayloadSearch::Incomplete,
+    };
+
+    PayloadSearch::Found(PayloadPosition {
+        len_end: index,
+        start,
+        len,
+        quoted,
+    })
+}
+
+/// Remove `buf[..end]` from `buf`, returning `buf[..data_end]` as the URC
+fn take_urc<const L: usize>(
+    buf: &mut Vec<u8, L>,
+    data_end: usize,
+    end: usize,
+) -> UrcMatcherResult<L> {
+    let urc = Vec::from_slice(&buf[..data_end]).unwrap();
+    *buf = Vec::from_slice(&buf[end..]).unwrap();
+    UrcMatcherResult::Complete(urc)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const TEST_RX_BUF_LEN: usize = 64;
+
+    fn complete(urc: &[u8]) -> Vec<u8, TEST_RX_BUF_LEN> {
+        Vec::from_slice(urc).unwrap()
+    }
+
+    struct LinesMatcher(&'static [u8], usize);
+
+    impl UrcMatcher for LinesMatcher {
+        fn process<const L: usize>(&mut self, buf: &mut Vec<u8, L>) -> UrcMatcherResult<L> {
+            if buf.starts_with(self.0) {
+                match_lines(buf, self.1)
+            } else {
+                UrcMatcherResult::NotHandled
+            }
+        }
+    }
+
+    #[test]
+    fn chain() {
+        let mut matcher = (
+            DefaultUrcMatcher,
+            LinesMatcher(b"+FOO", 2),
+            LinesMatcher(b"+BAR", 1),
+        );
+
+        let mut buf = Vec::<u8, TEST_RX_BUF_LEN>::from_slice(b"+FOO: 1\r\n").unwrap();
+        assert!(matches!(
+            matcher.process(&mut buf),
+            UrcMatcherResult::Incomplete
+        ));
+
+        buf.extend_from_slice(b"data\r\n").unwrap();
+        match matcher.process(&mut buf) {
+            UrcMatcherResult::Complete(urc) => assert_eq!(urc, complete(b"+FOO: 1\r\ndata")),
+            _ => panic!("expected complete URC"),
+        }
+
+        let mut buf = Vec::<u8, TEST_RX_BUF_LEN>::from_slice(b"+BAR: 1\r\n").unwrap();
+        match matcher.process(&mut buf) {
+            UrcMatcherResult::Complete(urc) => assert_eq!(urc, complete(b"+BAR: 1")),
+            _ => panic!("expected complete URC"),
+        }
+
+        let mut buf = Vec::<u8, TEST_RX_BUF_LEN>::from_slice(b"+BAZ: 1\r\n").unwrap();
+        assert!(matches!(
+            matcher.process(&mut buf),
+            UrcMatcherResult::NotHandled
+        ));
+    }
+
+    #[test]
+    fn lines() {
+        let mut buf = Vec::<u8, TEST_RX_BUF_LEN>::from_slice(b"+QIURC: \"recv\",0\r\nte").unwrap();
+        assert!(matches!(
+            match_lines(&mut buf, 2),
+            UrcMatcherResult::Incomplete
+        ));
+
+        buf.extend_from_slice(b"st\r\nOK\r\n").unwrap();
+        match match_lines(&mut buf, 2) {
+            UrcMatcherResult::Complete(urc) => {
+                assert_eq!(urc, complete(b"+QIURC: \"recv\",0\r\ntest"))
+            }
+            _ => panic!("expected complete URC"),
+        }
+        assert_eq!(buf, complete(b"OK\r\n"));
+
+        assert!(matches!(
+            match_lines(&mut buf, 0),
+            UrcMatcherResult::NotHandled
+        ));
+        assert_eq!(buf, complete(b"OK\r\n"));
+    }
+
+    #[test]
+    fn payload_len_quoted() {
+        let mut buf =
+            Vec::<u8, TEST_RX_BUF_LEN>::from_slice(b"+USORF: 0,\"1.2.3.4\",5000,7,\"a,\"\r\nb")
+                .unwrap();
+        assert!(matches!(
+            match_payload_len(&mut buf, 3),
+            UrcMatcherResult::Incomplete
+        ));
+
+        buf.extend_from_slice(b"c\"\r\n+CREG: 1").unwrap();
+        match match_payload_len(&mut buf, 3) {
+            UrcMatcherResult::Complete(urc) => assert_eq!(
+                urc,
+                complete(b"+USORF: 0,\"1.2.3.4\",5000,7,\"a,\"\r\nbc\"")
+            ),
+            _ => panic!("expected complete URC"),
+        }
+        assert_eq!(buf, complete(b"+CREG: 1"));
+    }
+
+    #[test]
+    fn payload_len_next_line() {
+        let mut buf = Vec::<u8, TEST_RX_BUF_LEN>::from_slice(b"+QIRD: 4\r\n\r\nab\r\n").unwrap();
+        match match_payload_len(&mut buf, 0) {
+            UrcMatcherResult::Complete(urc) => assert_eq!(urc, complete(b"+QIRD: 4\r\n\r\nab")),
+            _ => panic!("expected complete URC"),
+        }
+        assert!(buf.is_empty());
+
+        let mut buf = Vec::<u8, TEST_RX_BUF_LEN>::from_slice(b"+QIRD: x\r\n").unwrap();
+        assert!(matches!(
+            match_payload_len(&mut buf, 0),
+            UrcMatcherResult::NotHandled
+        ));
+    }
+
+    #[test]
+    fn payload_len_exceeding_buffer() {
+        let mut buf = Vec::<u8, TEST_RX_BUF_LEN>::from_slice(b"+QIRD: 64\r\nab").unwrap();
+        assert!(matches!(
+            match_payload_len(&mut buf, 0),
+            UrcMatcherResult::NotHandled
+        ));
+
+        let mut buf = Vec::<u8, TEST_RX_BUF_LEN>::from_slice(
+            b"+USORF: 0,\"1.2.3.4\",5000,18446744073709551615,\"ab",
+        )
+        .unwrap();
+        assert!(matches!(
+            match_payload_len(&mut buf, 3),
+            UrcMatcherResult::NotHandled
+        ));
+    }
+}
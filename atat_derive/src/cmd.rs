@@ -8,6 +8,7 @@ use crate::parse::{CmdAttributes, ParseInput};
 pub fn atat_cmd(input: TokenStream) -> TokenStream {
     let ParseInput {
         ident,
+        vis,
         at_cmd,
         generics,
         variants,
@@ -24,6 +25,8 @@ pub fn atat_cmd(input: TokenStream) -> TokenStream {
         value_sep,
         cmd_prefix,
         termination,
+        read,
+        test,
     } = at_cmd.expect("missing #[at_cmd(...)] attribute");
 
     let ident_str = ident.to_string();
@@ -59,21 +62,172 @@ pub fn atat_cmd(input: TokenStream) -> TokenStream {
         None => quote! {},
     };
 
+    // Responses are prefixed by the command, eg. `+CMD: `
+    let prefix_len = cmd.len() + 2;
+    let max_response_len = |resp: &syn::Type| {
+        quote! {
+            const MAX_RESPONSE_LEN: Option<usize> = match <#resp as atat::AtatResp>::MAX_LEN {
+                // Leading and trailing line endings, and `\r\nOK\r\n`
+                Some(len) => Some(#prefix_len + len + 10),
+                None => None,
+            };
+        }
+    };
+
     let subcmd_len = cmd.len().max(1);
     let mut cmd_len = cmd_prefix.len() + cmd.len() + termination.len();
     if value_sep {
         cmd_len += 1;
     }
 
+    let serialize_options = quote! {
+        atat::serde_at::SerializeOptions {
+            value_sep: #value_sep,
+            cmd_prefix: #cmd_prefix,
+            termination: #termination
+        }
+    };
+
     let err = error.unwrap_or_else(|| syn::parse_str("atat::GenericError").unwrap());
 
-    let (field_names, field_names_str): (Vec<_>, Vec<_>) = variants
+    let tuple_struct = variants.iter().any(|f| f.ident.is_none());
+
+    let serialize_fields: Vec<_> = variants
         .iter()
-        .map(|f| {
-            let ident = f.ident.clone().unwrap();
-            (ident.clone(), ident.to_string())
+        .enumerate()
+        .map(|(i, f)| match f.ident {
+            Some(ref ident) => {
+                let ident_str = ident.to_string();
+                quote! {
+                    atat::serde_at::serde::ser::SerializeStruct::serialize_field(
+                        &mut serde_state,
+                        #ident_str,
+                        &self.#ident,
+                    )?;
+                }
+            }
+            None => {
+                let index = syn::Index::from(i);
+                quote! {
+                    atat::serde_at::serde::ser::SerializeTupleStruct::serialize_field(
+                        &mut serde_state,
+                        &self.#index,
+                    )?;
+                }
+            }
         })
-        .unzip();
+        .collect();
+
+    let serialize = if tuple_struct {
+        quote! {
+            let mut serde_state = atat::serde_at::serde::Serializer::serialize_tuple_struct(
+                serializer,
+                #ident_str,
+                #n_fields,
+            )?;
+
+            #(#serialize_fields)*
+
+            atat::serde_at::serde::ser::SerializeTupleStruct::end(serde_state)
+        }
+    } else {
+        quote! {
+            let mut serde_state = atat::serde_at::serde::Serializer::serialize_struct(
+                serializer,
+                #ident_str,
+                #n_fields,
+            )?;
+
+            #(#serialize_fields)*
+
+            atat::serde_at::serde::ser::SerializeStruct::end(serde_state)
+        }
+    };
+
+    let parse = quote! {
+        #[inline]
+        fn parse(&self, res: Result<&[u8], &atat::InternalError>) -> core::result::Result<Self::Response, atat::Error<Self::Error>> {
+            match res {
+                Ok(resp) => atat::serde_at::from_slice::<Self::Response>(resp).map_err(|e| {
+                    atat::Error::Parse
+                }),
+                Err(e) => Err(e.into())
+            }
+        }
+    };
+
+    // Companion read (`AT+CMD?`) and test (`AT+CMD=?`) commands, along with
+    // constructors for them on the derived type
+    let (companion_cmds, companion_fns): (Vec<_>, Vec<_>) = [
+        ("Read", "read", "?", "Read command", read),
+        ("Test", "test", "=?", "Test command", test),
+    ]
+    .iter()
+    .filter_map(|(suffix, fn_name, query, kind, resp)| {
+        let resp = resp.as_ref()?;
+        let companion_ident = format_ident!("{}{}", ident, suffix);
+        let fn_ident = format_ident!("{}", fn_name);
+        let companion_str = format!("{}{}{}{}", cmd_prefix, cmd, query, termination);
+        let companion_len = companion_str.len();
+        let companion_bytes = proc_macro2::Literal::byte_string(companion_str.as_bytes());
+        let companion_max_response_len = max_response_len(resp);
+        let doc = format!(
+            "{} (`{}{}{}`) of [`{}`]",
+            kind, cmd_prefix, cmd, query, ident
+        );
+
+        Some((
+            quote! {
+                #[doc = #doc]
+                #[derive(Debug, Clone)]
+                #vis struct #companion_ident;
+
+                #[automatically_derived]
+                impl atat::AtatCmd<#companion_len> for #companion_ident {
+                    type Response = #resp;
+                    type Error = #err;
+
+                    #timeout
+
+                    #abortable
+
+                    #force_receive
+
+                    #companion_max_response_len
+
+                    #[inline]
+                    fn as_bytes(&self) -> atat::heapless::Vec<u8, #companion_len> {
+                        atat::heapless::Vec::from_slice(#companion_bytes).unwrap()
+                    }
+
+                    #parse
+                }
+            },
+            quote! {
+                #[doc = #doc]
+                #[inline]
+                #vis fn #fn_ident() -> #companion_ident {
+                    #companion_ident
+                }
+            },
+        ))
+    })
+    .unzip();
+
+    let companions = if companion_cmds.is_empty() {
+        quote! {}
+    } else {
+        quote! {
+            #(#companion_cmds)*
+
+            #[automatically_derived]
+            impl #impl_generics #ident #ty_generics #where_clause {
+                #(#companion_fns)*
+            }
+        }
+    };
+
+    let main_max_response_len = max_response_len(&resp);
 
     let struct_len = crate::len::struct_len(variants, n_fields.checked_sub(1).unwrap_or(n_fields));
 
@@ -98,30 +252,48 @@ pub fn atat_cmd(input: TokenStream) -> TokenStream {
 
             #force_receive
 
+            #main_max_response_len
+
             #[inline]
             fn as_bytes(&self) -> atat::heapless::Vec<u8, { #ident_len + #cmd_len }> {
-                let s: atat::heapless::String<#subcmd_len> = atat::heapless::String::from(#cmd);
-                match atat::serde_at::to_vec(self, s, atat::serde_at::SerializeOptions {
-                    value_sep: #value_sep,
-                    cmd_prefix: #cmd_prefix,
-                    termination: #termination
-                }) {
+                match atat::AtatCmd::try_as_bytes(self) {
                     Ok(s) => s,
                     Err(_) => panic!("Failed to serialize command")
                 }
             }
 
             #[inline]
-            fn parse(&self, res: Result<&[u8], &atat::InternalError>) -> core::result::Result<Self::Response, atat::Error<Self::Error>> {
-                match res {
-                    Ok(resp) => atat::serde_at::from_slice::<#resp>(resp).map_err(|e| {
-                        atat::Error::Parse
-                    }),
-                    Err(e) => Err(e.into())
-                }
+            fn try_as_bytes(&self) -> core::result::Result<atat::heapless::Vec<u8, { #ident_len + #cmd_len }>, atat::Error<Self::Error>> {
+                let s: atat::heapless::String<#subcmd_len> = atat::heapless::String::from(#cmd);
+                atat::serde_at::to_vec(self, s, #serialize_options).map_err(|_| atat::Error::Serialize)
             }
+
+            #[inline]
+            fn write(&self, buf: &mut [u8]) -> core::result::Result<usize, atat::Error<Self::Error>> {
+                atat::serde_at::to_slice(self, #cmd, #serialize_options, buf).map_err(|_| atat::Error::Serialize)
+            }
+
+            #[inline]
+            fn write_to<F>(&self, write: F) -> core::result::Result<(), atat::Error<Self::Error>>
+            where
+                F: FnMut(&[u8]) -> core::result::Result<(), atat::Error<Self::Error>>,
+            {
+                // Check the length up front, so over-length commands are not
+                // partially written
+                let mut len = atat::derive::LenWriter::new(#ident_len + #cmd_len);
+                atat::serde_at::to_writer(self, #cmd, #serialize_options, &mut len)
+                    .map_err(|_| atat::Error::Serialize)?;
+
+                let mut writer = atat::derive::FnWriter::new(write);
+                atat::serde_at::to_writer(self, #cmd, #serialize_options, &mut writer)
+                    .map_err(|_| writer.take_error().unwrap_or(atat::Error::Serialize))
+            }
+
+            #parse
         }
 
+        #companions
+
         #[automatically_derived]
         impl #impl_generics atat::serde_at::serde::Serialize for #ident #ty_generics #where_clause {
             #[inline]
@@ -132,21 +304,7 @@ pub fn atat_cmd(input: TokenStream) -> TokenStream {
             where
                 S: atat::serde_at::serde::Serializer,
             {
-                let mut serde_state = atat::serde_at::serde::Serializer::serialize_struct(
-                    serializer,
-                    #ident_str,
-                    #n_fields,
-                )?;
-
-                #(
-                    atat::serde_at::serde::ser::SerializeStruct::serialize_field(
-                        &mut serde_state,
-                        #field_names_str,
-                        &self.#field_names,
-                    )?;
-                )*
-
-                atat::serde_at::serde::ser::SerializeStruct::end(serde_state)
+                #serialize
             }
         }
     })
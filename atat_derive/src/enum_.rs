@@ -4,7 +4,7 @@ use syn::{parse_macro_input, parse_quote, Field, Fields, Ident, PathSegment, Typ
 
 use crate::{
     helpers,
-    parse::{ArgAttributes, EnumAttributes, ParseInput},
+    parse::{ArgAttributes, ArgValue, EnumAttributes, ParseInput, Variant},
 };
 
 struct AnonymousEnum {
@@ -28,11 +28,20 @@ pub fn atat_enum(input: TokenStream) -> TokenStream {
         ..
     } = parse_macro_input!(input as ParseInput);
 
-    let repr = at_enum
-        .unwrap_or_else(|| EnumAttributes {
-            repr: format_ident!("u8"),
-        })
-        .repr;
+    let EnumAttributes { repr, quoted } = at_enum.unwrap_or_default();
+
+    if variants.iter().any(|v| {
+        matches!(
+            v.attrs.at_arg,
+            Some(ArgAttributes {
+                value: Some(ArgValue::Str(_)),
+                ..
+            })
+        )
+    }) {
+        return string_enum(&ident, &variants, &generics, quoted);
+    }
+
     let ident_str = ident.to_string();
 
     let variant_names_str: Vec<_> = variants
@@ -92,10 +101,25 @@ pub fn atat_enum(input: TokenStream) -> TokenStream {
     let (serialize_impl_generics, serialize_ty_generics, serialize_where_clause) =
         serialize_generics.split_for_impl();
 
+    let mut other_variant = None;
+
     for (i, variant) in variants.iter().enumerate() {
         let variant_ident = variant.ident.clone().unwrap();
         let variant_ident_str = variant_ident.to_string();
-        let val = if let Some(ArgAttributes { value: Some(v), .. }) = variant.attrs.at_arg {
+
+        if let Some(ArgAttributes { other: true, .. }) = variant.attrs.at_arg {
+            if other_variant.is_some() {
+                panic!("Cannot have more than one `other` variant!")
+            }
+            check_other_variant(variant);
+            other_variant = Some(variant_ident);
+            continue;
+        }
+        let val = if let Some(ArgAttributes {
+            value: Some(ArgValue::Int(v)),
+            ..
+        }) = variant.attrs.at_arg
+        {
             quote! { #v }
         } else {
             quote! { #ident::#variant_ident }
@@ -217,6 +241,36 @@ pub fn atat_enum(input: TokenStream) -> TokenStream {
         info.anonymous_enum.fields.push(anon_ident);
     }
 
+    // Unknown values are captured by the `other` variant, rather than
+    // rejected
+    let (other_field, identifier_fallback) = if let Some(ref other_ident) = other_variant {
+        info.deserialize_match_arms.push(quote! {
+            (#anon_enum::__other(value), _) => core::convert::TryFrom::try_from(value)
+                .map(#ident::#other_ident)
+                .map_err(|_| atat::serde_at::serde::de::Error::invalid_value(
+                    atat::serde_at::serde::de::Unexpected::Signed(value),
+                    &#invalid_val_err,
+                ))
+        });
+        info.serialize_match_arms.push(quote! {
+            #ident::#other_ident(ref value) => atat::serde_at::serde::Serialize::serialize(value, serializer)
+        });
+        (
+            quote! { __other(i64), },
+            quote! { Ok(#anon_enum::__other(value)) },
+        )
+    } else {
+        (
+            quote! {},
+            quote! {
+                Err(atat::serde_at::serde::de::Error::invalid_value(
+                    atat::serde_at::serde::de::Unexpected::Signed(value),
+                    &#invalid_val_err,
+                ))
+            },
+        )
+    };
+
     let enum_len = crate::len::enum_len(&variants, &repr, &mut atat_len_generics);
 
     let Info {
@@ -314,6 +368,7 @@ pub fn atat_enum(input: TokenStream) -> TokenStream {
                 #[allow(non_camel_case_types)]
                 enum #anon_ident {
                     #(#anon_fields,)*
+                    #other_field
                 }
                 struct #field_visitor;
                 impl<'de> atat::serde_at::serde::de::Visitor<'de> for #field_visitor {
@@ -335,10 +390,7 @@ pub fn atat_enum(input: TokenStream) -> TokenStream {
                     {
                         match value {
                             #(#identifier_match_arms,)*
-                            _ => Err(atat::serde_at::serde::de::Error::invalid_value(
-                                atat::serde_at::serde::de::Unexpected::Signed(value),
-                                &#invalid_val_err,
-                            )),
+                            _ => #identifier_fallback,
                         }
                     }
                 }
@@ -395,3 +447,217 @@ pub fn atat_enum(input: TokenStream) -> TokenStream {
         }
     })
 }
+
+/// Derive an enum of unit variants, each represented by a string given as
+/// `#[at_arg(value = "..")]`, eg. `"IPV4V6"`. Values are quoted unless the
+/// enum is annotated with `#[at_enum(quoted = false)]`.
+fn string_enum(
+    ident: &Ident,
+    variants: &[Variant],
+    generics: &syn::Generics,
+    quoted: bool,
+) -> TokenStream {
+    let other_variant = variants.iter().find(|variant| {
+        matches!(
+            variant.attrs.at_arg,
+            Some(ArgAttributes { other: true, .. })
+        )
+    });
+    if let Some(variant) = other_variant {
+        check_other_variant(variant);
+    }
+
+    let (variant_idents, values): (Vec<_>, Vec<_>) = variants
+        .iter()
+        .filter(|variant| !matches!(variant.attrs.at_arg, Some(ArgAttributes { other: true, .. })))
+        .map(|variant| {
+            let variant_ident = variant.ident.clone().unwrap();
+            match (&variant.fields, &variant.attrs.at_arg) {
+                (
+                    Some(Fields::Unit),
+                    Some(ArgAttributes {
+                        value: Some(ArgValue::Str(v)),
+                        ..
+                    }),
+                ) => (variant_ident, v.clone()),
+                _ => panic!(
+                    "All variants of a string valued enum must be unit variants with a string value, eg. #[at_arg(value = \"{}\")]",
+                    variant_ident
+                ),
+            }
+        })
+        .unzip();
+
+    let max_len = values.iter().map(String::len).max().unwrap_or(0);
+    let mut enum_len = if quoted {
+        quote! { #max_len + 2 }
+    } else {
+        quote! { #max_len }
+    };
+
+    // Unknown values are captured by the `other` variant using `FromStr`,
+    // rather than rejected
+    let (other_serialize_arm, other_deserialize_arm) = if let Some(variant) = other_variant {
+        let other_ident = variant.ident.clone().unwrap();
+        let other_ty = other_field_type(variant);
+        enum_len = quote! {
+            {
+                const e_len: usize = #enum_len;
+                const o_len: usize = <#other_ty as atat::AtatLen>::LEN;
+                if o_len < e_len { e_len } else { o_len }
+            }
+        };
+        let serialize_arm = if quoted {
+            quote! {
+                #ident::#other_ident(ref value) => return atat::serde_at::serde::Serialize::serialize(value, serializer),
+            }
+        } else {
+            quote! {
+                #ident::#other_ident(ref value) => core::convert::AsRef::<str>::as_ref(value),
+            }
+        };
+        (
+            serialize_arm,
+            quote! {
+                _ => core::str::from_utf8(value)
+                    .ok()
+                    .and_then(|v| core::str::FromStr::from_str(v).ok())
+                    .map(#ident::#other_ident)
+                    .ok_or_else(|| atat::serde_at::serde::de::Error::unknown_variant(
+                        core::str::from_utf8(value).unwrap_or(""),
+                        VARIANTS,
+                    )),
+            },
+        )
+    } else {
+        (
+            quote! {},
+            quote! {
+                _ => Err(atat::serde_at::serde::de::Error::unknown_variant(
+                    core::str::from_utf8(value).unwrap_or(""),
+                    VARIANTS,
+                )),
+            },
+        )
+    };
+
+    let values_bytes: Vec<_> = values
+        .iter()
+        .map(|v| proc_macro2::Literal::byte_string(v.as_bytes()))
+        .collect();
+
+    let serialize_value = if quoted {
+        quote! { atat::serde_at::serde::Serializer::serialize_str(serializer, value) }
+    } else {
+        quote! { atat::serde_at::serde::Serializer::serialize_bytes(serializer, value.as_bytes()) }
+    };
+
+    let default_impl = variants
+        .iter()
+        .find(|variant| {
+            matches!(
+                variant.attrs.at_arg,
+                Some(ArgAttributes { default: true, .. })
+            )
+        })
+        .map(|variant| {
+            let variant_ident = variant.ident.clone().unwrap();
+            quote! {
+                #[automatically_derived]
+                impl Default for #ident {
+                    fn default() -> Self {
+                        #ident::#variant_ident
+                    }
+                }
+            }
+        });
+
+    let visitor = format_ident!("{}Visitor", ident);
+    let enum_name = format!("enum {}", ident);
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+
+    TokenStream::from(quote! {
+        #default_impl
+
+        #[automatically_derived]
+        impl #impl_generics atat::AtatLen for #ident #ty_generics #where_clause {
+            const LEN: usize = #enum_len;
+        }
+
+        #[automatically_derived]
+        impl #impl_generics atat::serde_at::serde::Serialize for #ident #ty_generics #where_clause {
+            #[inline]
+            fn serialize<S>(&self, serializer: S) -> core::result::Result<S::Ok, S::Error>
+            where
+                S: atat::serde_at::serde::Serializer
+            {
+                let value = match *self {
+                    #(#ident::#variant_idents => #values,)*
+                    #other_serialize_arm
+                };
+                #serialize_value
+            }
+        }
+
+        #[automatically_derived]
+        impl<'de> atat::serde_at::serde::Deserialize<'de> for #ident {
+            fn deserialize<D>(deserializer: D) -> core::result::Result<Self, D::Error>
+            where
+                D: atat::serde_at::serde::Deserializer<'de>,
+            {
+                const VARIANTS: &'static [&'static str] = &[#(#values),*];
+
+                struct #visitor;
+
+                impl<'de> atat::serde_at::serde::de::Visitor<'de> for #visitor {
+                    type Value = #ident;
+
+                    fn expecting(
+                        &self,
+                        formatter: &mut core::fmt::Formatter,
+                    ) -> core::fmt::Result {
+                        core::fmt::Formatter::write_str(formatter, #enum_name)
+                    }
+
+                    fn visit_str<E>(self, value: &str) -> core::result::Result<Self::Value, E>
+                    where
+                        E: atat::serde_at::serde::de::Error,
+                    {
+                        self.visit_bytes(value.as_bytes())
+                    }
+
+                    fn visit_bytes<E>(self, value: &[u8]) -> core::result::Result<Self::Value, E>
+                    where
+                        E: atat::serde_at::serde::de::Error,
+                    {
+                        match value {
+                            #(#values_bytes => Ok(#ident::#variant_idents),)*
+                            #other_deserialize_arm
+                        }
+                    }
+                }
+
+                atat::serde_at::serde::Deserializer::deserialize_identifier(deserializer, #visitor)
+            }
+        }
+    })
+}
+
+/// Type of the single field of an `#[at_arg(other)]` variant
+fn other_field_type(variant: &Variant) -> Type {
+    match variant.fields {
+        Some(Fields::Unnamed(ref fields)) if fields.unnamed.len() == 1 => {
+            fields.unnamed[0].ty.clone()
+        }
+        _ => panic!(
+            "The `other` variant must be a tuple variant with a single field, eg. `Unknown(u8)`"
+        ),
+    }
+}
+
+fn check_other_variant(variant: &Variant) {
+    other_field_type(variant);
+    if let Some(ArgAttributes { value: Some(_), .. }) = variant.attrs.at_arg {
+        panic!("The `other` variant cannot have a value");
+    }
+}
@@ -0,0 +1,102 @@
+use crate::proc_macro::TokenStream;
+use quote::quote;
+use syn::parse_macro_input;
+
+use crate::{
+    helpers,
+    parse::{ArgAttributes, ArgValue, ParseInput},
+};
+
+pub fn atat_flags(input: TokenStream) -> TokenStream {
+    let ParseInput {
+        ident,
+        vis,
+        at_enum,
+        generics,
+        variants,
+        ..
+    } = parse_macro_input!(input as ParseInput);
+
+    let repr = at_enum.unwrap_or_default().repr;
+
+    let (field_names, masks): (Vec<_>, Vec<_>) = variants
+        .iter()
+        .map(|f| {
+            let ident = f
+                .ident
+                .clone()
+                .expect("AtatFlags can only be derived for structs with named fields");
+            match f.attrs.at_arg {
+                Some(ArgAttributes {
+                    value: Some(ArgValue::Int(mask)),
+                    ..
+                }) => (ident, proc_macro2::Literal::i64_unsuffixed(mask)),
+                _ => panic!(
+                    "Flag `{}` must have an integer mask, eg. #[at_arg(value = 0x01)]",
+                    ident
+                ),
+            }
+        })
+        .unzip();
+
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+    let mut serde_generics = generics.clone();
+    helpers::add_lifetime(&mut serde_generics, "'de");
+    let (serde_impl_generics, _, _) = serde_generics.split_for_impl();
+
+    TokenStream::from(quote! {
+        #[automatically_derived]
+        impl #impl_generics #ident #ty_generics #where_clause {
+            /// The integer representation of the set flags
+            #[must_use]
+            #vis fn bits(&self) -> #repr {
+                let mut bits: #repr = 0;
+                #(
+                    if self.#field_names {
+                        bits |= #masks;
+                    }
+                )*
+                bits
+            }
+
+            /// Construct the flags from their integer representation, ignoring
+            /// any unknown bits
+            #[must_use]
+            #vis fn from_bits(bits: #repr) -> Self {
+                Self {
+                    #(
+                        #field_names: bits & #masks == #masks,
+                    )*
+                }
+            }
+        }
+
+        #[automatically_derived]
+        impl #impl_generics atat::AtatLen for #ident #ty_generics #where_clause {
+            const LEN: usize = <#repr as atat::AtatLen>::LEN;
+        }
+
+        #[automatically_derived]
+        impl #impl_generics atat::serde_at::serde::Serialize for #ident #ty_generics #where_clause {
+            #[inline]
+            fn serialize<S>(&self, serializer: S) -> core::result::Result<S::Ok, S::Error>
+            where
+                S: atat::serde_at::serde::Serializer,
+            {
+                atat::serde_at::serde::Serialize::serialize(&self.bits(), serializer)
+            }
+        }
+
+        #[automatically_derived]
+        impl #serde_impl_generics atat::serde_at::serde::Deserialize<'de> for #ident #ty_generics #where_clause {
+            #[inline]
+            fn deserialize<D>(deserializer: D) -> core::result::Result<Self, D::Error>
+            where
+                D: atat::serde_at::serde::Deserializer<'de>,
+            {
+                <#repr as atat::serde_at::serde::Deserialize<'de>>::deserialize(deserializer)
+                    .map(Self::from_bits)
+            }
+        }
+    })
+}
@@ -62,6 +62,10 @@ pub fn add_type_parameter_bound(
 }
 
 pub fn deserialize_struct(ident: &Ident, variants: &[Variant], generics: &Generics) -> TokenStream {
+    if variants.iter().any(|f| f.ident.is_none()) {
+        return deserialize_tuple_struct(ident, variants, generics);
+    }
+
     let ident_str = ident.to_string();
 
     let (field_names, field_names_str): (Vec<_>, Vec<_>) = variants
@@ -276,3 +280,72 @@ pub fn deserialize_struct(ident: &Ident, variants: &[Variant], generics: &Generi
         )
     }
 }
+
+/// Deserialize a tuple struct, eg. `struct Foo(u8, String<16>)`, from a
+/// sequence of values
+pub fn deserialize_tuple_struct(
+    ident: &Ident,
+    variants: &[Variant],
+    generics: &Generics,
+) -> TokenStream {
+    let ident_str = ident.to_string();
+
+    let field_types: Vec<_> = variants.iter().map(|f| f.ty.clone()).collect();
+    let (anon_field_ind, anon_field): (Vec<usize>, Vec<Ident>) = variants
+        .iter()
+        .enumerate()
+        .map(|(i, _)| (i, format_ident!("__field{}", i)))
+        .unzip();
+
+    let len = variants.len();
+    let visitor = format_ident!("{}Visitor", ident);
+    let invalid_len_err = format!("tuple struct {} with {} elements", ident, len);
+    let struct_name = format!("tuple struct {}", ident);
+
+    let (_, ty_generics, _) = generics.split_for_impl();
+    let mut serde_generics = generics.clone();
+    add_lifetime(&mut serde_generics, "'de");
+    let (serde_impl_generics, serde_ty_generics, _) = serde_generics.split_for_impl();
+
+    quote! {
+        struct #visitor #serde_impl_generics {
+            marker: core::marker::PhantomData<#ident #ty_generics>,
+            lifetime: core::marker::PhantomData<&'de ()>,
+        }
+        impl #serde_impl_generics atat::serde_at::serde::de::Visitor<'de> for #visitor #serde_ty_generics {
+            type Value = #ident #ty_generics;
+            fn expecting(
+                &self,
+                formatter: &mut core::fmt::Formatter,
+            ) -> core::fmt::Result {
+                core::fmt::Formatter::write_str(formatter, #struct_name)
+            }
+            #[inline]
+            fn visit_seq<A>(
+                self,
+                mut seq: A,
+            ) -> core::result::Result<Self::Value, A::Error>
+            where
+                A: atat::serde_at::serde::de::SeqAccess<'de>,
+            {
+                #(
+                    let #anon_field =
+                        atat::serde_at::serde::de::SeqAccess::next_element::<#field_types>(&mut seq)?.ok_or_else(||atat::serde_at::serde::de::Error::invalid_length(
+                            #anon_field_ind,
+                            &#invalid_len_err,
+                        ))?;
+                )*
+                Ok(#ident(#(#anon_field),*))
+            }
+        }
+        atat::serde_at::serde::Deserializer::deserialize_tuple_struct(
+            deserializer,
+            #ident_str,
+            #len,
+            #visitor {
+                marker: core::marker::PhantomData::<#ident #ty_generics>,
+                lifetime: core::marker::PhantomData,
+            },
+        )
+    }
+}
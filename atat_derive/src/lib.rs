@@ -41,6 +41,7 @@ extern crate proc_macro2;
 
 mod cmd;
 mod enum_;
+mod flags;
 mod helpers;
 mod len;
 mod parse;
@@ -52,7 +53,17 @@ use crate::proc_macro::TokenStream;
 /// Automatically derive [`atat::AtatResp`] trait
 ///
 /// [`atat::AtatResp`]: ../atat/trait.AtatResp.html
-#[proc_macro_derive(AtatResp, attributes(at_arg))]
+///
+/// ### Container attribute (`#[at_resp(..)]`)
+/// Optionally, the maximum length of the response can be given, allowing the
+/// client to check that it fits in the ingress buffer:
+/// - `max_len`: Calculate the maximum length from the fields. This requires
+///   all fields to implement [`atat::AtatLen`], or to have a `#[at_arg(len =
+///   ..)]` attribute.
+/// - `max_len = <integer>`: Set the maximum length explicitly.
+///
+/// [`atat::AtatLen`]: ../atat/derive/trait.AtatLen.html
+#[proc_macro_derive(AtatResp, attributes(at_resp, at_arg))]
 pub fn derive_atat_resp(input: TokenStream) -> TokenStream {
     resp::atat_resp(input)
 }
@@ -60,7 +71,36 @@ pub fn derive_atat_resp(input: TokenStream) -> TokenStream {
 /// Automatically derive [`atat::AtatUrc`] trait
 ///
 /// [`atat::AtatUrc`]: ../atat/trait.AtatUrc.html
-#[proc_macro_derive(AtatUrc, attributes(at_urc))]
+///
+/// Each variant is annotated with the URC code, eg. `#[at_urc("+UUSORD")]`.
+/// Variants can be unit variants, tuple variants with a single response type,
+/// or tuple and struct variants holding the URC parameters directly, eg.
+/// `SocketData { socket: u8, length: usize }`. Fields of struct variants can
+/// be reordered with `#[at_arg(position = ..)]`.
+///
+/// URCs that cannot be captured by the default digester can additionally be
+/// annotated with either `lines = n`, for URCs spanning `n` lines, or
+/// `payload_len_field = n`, for URCs carrying a payload with its length given
+/// by the parameter at index `n`. These are handled by the generated
+/// `<Ident>UrcMatcher` implementing [`atat::UrcMatcher`], available through
+/// `<Ident>::matcher()`. The matcher is generated for every URC enum, leaving
+/// all URCs to the digester if no variant is annotated:
+///
+/// [`atat::UrcMatcher`]: ../atat/trait.UrcMatcher.html
+///
+/// ```ignore
+/// #[derive(AtatUrc)]
+/// pub enum Urc {
+///     // +USORF: 0,"1.2.3.4",5000,4,"data"
+///     #[at_urc("+USORF", payload_len_field = 3)]
+///     SocketData(u8, String<16>, u16, usize, String<64>),
+/// }
+///
+/// let client = ClientBuilder::new(tx, timer, config)
+///     .with_custom_urc_matcher(Urc::matcher())
+///     .build(queues);
+/// ```
+#[proc_macro_derive(AtatUrc, attributes(at_urc, at_arg))]
 pub fn derive_atat_urc(input: TokenStream) -> TokenStream {
     urc::atat_urc(input)
 }
@@ -114,17 +154,71 @@ pub fn derive_atat_urc(input: TokenStream) -> TokenStream {
 ///
 /// **Note**: `at_enum` defaults to `u8`
 ///
+/// For string valued enums, `#[at_enum(quoted = false)]` serializes the values
+/// as bare tokens, eg. `READY`, rather than quoted strings, eg. `"IPV4V6"`.
+///
 /// ### Field attribute (`#[at_arg(..)]`)
 /// The `AtatEnum` derive macro comes with an optional field attribute
 /// `#[at_arg(..)]`, that can be specified o some or all of the fields.
 ///
 /// Allowed options for `at_arg` are:
-/// - `value` **integer** The value of the serialized field
+/// - `value` **integer** or **string** The value of the serialized field. If
+///   any variant has a string value, all variants must be unit variants with
+///   string values, and `AtatLen` is the length of the longest string.
+/// - `other` Mark a tuple variant with a single field, eg. `Unknown(u8)`, as
+///   the catch-all for unrecognised values, instead of failing to parse. The
+///   field is converted with `TryFrom<i64>` for integer valued enums, and
+///   with `FromStr` for string valued enums.
+///
+/// Eg.
+/// ```ignore
+/// #[derive(AtatEnum)]
+/// pub enum PdpType {
+///     #[at_arg(value = "IP")]
+///     IPv4,
+///     #[at_arg(value = "IPV6")]
+///     IPv6,
+///     #[at_arg(value = "IPV4V6")]
+///     IPv4v6,
+/// }
+/// ```
 #[proc_macro_derive(AtatEnum, attributes(at_enum, at_arg))]
 pub fn derive_atat_enum(input: TokenStream) -> TokenStream {
     enum_::atat_enum(input)
 }
 
+/// Automatically derive a bitflag parameter type, encoded as a single integer
+///
+/// Derived on a struct of `bool` fields, each annotated with the bit mask of
+/// the flag as `#[at_arg(value = 0x02)]`. This implements `Serialize`,
+/// `Deserialize` and [`atat::AtatLen`] through the integer representation,
+/// along with `bits()` and `from_bits(..)` conversions. Unknown bits are
+/// ignored when deserializing.
+///
+/// [`atat::AtatLen`]: ../atat/derive/trait.AtatLen.html
+///
+/// ### Container attribute (`#[at_enum(..)]`)
+/// The integer representation defaults to `u8`, and can be changed with
+/// `#[at_enum(u16)]`, like for [`AtatEnum`].
+///
+/// [`AtatEnum`]: derive.AtatEnum.html
+///
+/// Eg.
+/// ```ignore
+/// #[derive(AtatFlags)]
+/// #[at_enum(u16)]
+/// pub struct PowerSavingOptions {
+///     #[at_arg(value = 0x01)]
+///     pub uart_wakeup: bool,
+///     #[at_arg(value = 0x100)]
+///     pub deep_sleep: bool,
+/// }
+/// ```
+#[proc_macro_derive(AtatFlags, attributes(at_enum, at_arg))]
+pub fn derive_atat_flags(input: TokenStream) -> TokenStream {
+    flags::atat_flags(input)
+}
+
 /// Automatically derive [`atat::AtatCmd`] trait
 ///
 /// [`atat::AtatCmd`]: ../atat/trait.AtatCmd.html
@@ -136,7 +230,11 @@ pub fn derive_atat_enum(input: TokenStream) -> TokenStream {
 ///
 /// This container attribute requires specifying at least a command and an
 /// expected response struct as: `#[at_cmd("+USORD", SocketData)]` where
-/// `SocketData` is any type implementing `AtatResp`.
+/// `SocketData` is any type implementing `AtatResp`. Plain tuples can be used
+/// for simple responses, eg. `#[at_cmd("+CSQ", (u8, u8))]`.
+///
+/// Both structs with named fields and tuple structs are supported, eg.
+/// `struct SetFun(Functionality, Option<ResetMode>)`.
 ///
 /// Furthermore the container attribute allows specifying some additional
 /// options to tweak the command. All optional attributes takes the form `<key>
@@ -156,6 +254,16 @@ pub fn derive_atat_enum(input: TokenStream) -> TokenStream {
 ///   'AT'). Can also be set to '' (empty).
 /// - `termination`: **string** Overwrite the line termination of the command
 ///   (default '\r\n'). Can also be set to '' (empty).
+/// - `read`: **type** Generate a unit struct `<Name>Read`, implementing
+///   `AtatCmd` for the read command (eg. `AT+CFUN?`), with the given response
+///   type. It can also be constructed with `<Name>::read()`. The `timeout_ms`,
+///   `abortable` and `force_receive_state` options apply to it as well.
+/// - `test`: **type** Generate a unit struct `<Name>Test`, implementing
+///   `AtatCmd` for the test command (eg. `AT+CFUN=?`), with the given
+///   response type. It can also be constructed with `<Name>::test()`, and
+///   shares the options of the `read` command.
+///   Responses listing the supported values, eg. `+CFUN: (0,1,4),(0-1)`, can
+///   be parsed with `serde_at::RangeList`.
 ///
 /// ### Field attribute (`#[at_arg(..)]`)
 /// The `AtatCmd` derive macro comes with an optional field attribute
@@ -165,7 +273,7 @@ pub fn derive_atat_enum(input: TokenStream) -> TokenStream {
 /// - position: **integer** The index of the field in the resulting command
 ///   string. (eg. for command `AT+CMD=a,b`, field `a` would have `position = 1`
 ///   and field `b` would have `position = 2`) (defaults to order of the fields
-///   in the struct)
+///   in the struct). Not allowed on tuple struct fields.
 #[proc_macro_derive(AtatCmd, attributes(at_cmd, at_arg))]
 pub fn derive_atat_cmd(input: TokenStream) -> TokenStream {
     cmd::atat_cmd(input)
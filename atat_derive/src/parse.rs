@@ -2,23 +2,40 @@ use proc_macro2::Span;
 use syn::parse::{Error, Parse, ParseStream, Parser, Result};
 use syn::{
     parenthesized, Attribute, Data, DataEnum, DataStruct, DeriveInput, Fields, Generics, Ident,
-    Lit, LitByteStr, Path, Type,
+    Lit, LitByteStr, Path, Type, Visibility,
 };
 
 #[derive(Clone)]
 pub struct ParseInput {
     pub ident: Ident,
+    pub vis: Visibility,
     pub generics: Generics,
     pub at_cmd: Option<CmdAttributes>,
     pub at_enum: Option<EnumAttributes>,
+    pub at_resp: Option<RespAttributes>,
     pub variants: Vec<Variant>,
 }
 
+/// Parsed attributes of `#[at_resp(..)]`
+#[derive(Clone, Default)]
+pub struct RespAttributes {
+    pub max_len: Option<MaxLen>,
+}
+
+/// Maximum length of a response, given by `#[at_resp(max_len)]`
+#[derive(Clone)]
+pub enum MaxLen {
+    /// Calculated from the lengths of the fields
+    Fields,
+    /// Given explicitly, eg. `max_len = 64`
+    Fixed(usize),
+}
+
 /// Parsed attributes of `#[at_cmd(..)]`
 #[derive(Clone)]
 pub struct CmdAttributes {
     pub cmd: String,
-    pub resp: Path,
+    pub resp: Type,
     pub error: Option<Path>,
     pub timeout_ms: Option<u32>,
     pub abortable: Option<bool>,
@@ -26,26 +43,50 @@ pub struct CmdAttributes {
     pub value_sep: bool,
     pub cmd_prefix: String,
     pub termination: String,
+    pub read: Option<Type>,
+    pub test: Option<Type>,
 }
+/// Value of an enum variant, given by `#[at_arg(value = ..)]`
+#[derive(Clone)]
+pub enum ArgValue {
+    Int(i64),
+    Str(String),
+}
+
 /// Parsed attributes of `#[at_arg(..)]`
 #[derive(Clone)]
 pub struct ArgAttributes {
-    pub value: Option<i64>,
+    pub value: Option<ArgValue>,
     pub position: Option<usize>,
     pub len: Option<usize>,
     pub default: bool,
+    /// Catch-all variant for unknown values
+    pub other: bool,
 }
 
 /// Parsed attributes of `#[at_urc(..)]`
 #[derive(Clone)]
 pub struct UrcAttributes {
     pub code: LitByteStr,
+    pub lines: Option<usize>,
+    pub payload_len_field: Option<usize>,
 }
 
 /// Parsed attributes of `#[at_enum(..)]`
 #[derive(Clone)]
 pub struct EnumAttributes {
     pub repr: Ident,
+    /// Whether string values are quoted (default true)
+    pub quoted: bool,
+}
+
+impl Default for EnumAttributes {
+    fn default() -> Self {
+        Self {
+            repr: Ident::new("u8", Span::call_site()),
+            quoted: true,
+        }
+    }
 }
 
 /// Parsed field level attributes
@@ -99,7 +140,21 @@ fn sorted_variants(data: Data) -> Result<Vec<Variant>> {
         Data::Struct(DataStruct { fields, .. }) => {
             let unwrapped_fields = match fields {
                 Fields::Named(fields) => fields.named.iter().cloned().collect(),
-                Fields::Unnamed(fields) => fields.unnamed.iter().cloned().collect(),
+                Fields::Unnamed(fields) => {
+                    // The position of a tuple struct field is given by its index
+                    for f in &fields.unnamed {
+                        if let Some(ArgAttributes {
+                            position: Some(_), ..
+                        }) = parse_field_attr(&f.attrs)?.at_arg
+                        {
+                            return Err(Error::new(
+                                Span::call_site(),
+                                "position is not allowed on tuple struct fields",
+                            ));
+                        }
+                    }
+                    fields.unnamed.iter().cloned().collect()
+                }
                 Fields::Unit => Vec::new(),
             };
 
@@ -171,17 +226,19 @@ impl Parse for ArgAttributes {
             position: None,
             len: None,
             default: false,
+            other: false,
         };
 
         while {
             match input.parse::<syn::Meta>()? {
                 syn::Meta::NameValue(name_value) if name_value.path.is_ident("value") => {
                     match name_value.lit.clone() {
-                        Lit::Int(v) => attrs.value = Some(v.base10_parse().unwrap()),
+                        Lit::Int(v) => attrs.value = Some(ArgValue::Int(v.base10_parse().unwrap())),
+                        Lit::Str(v) => attrs.value = Some(ArgValue::Str(v.value())),
                         _ => {
                             return Err(Error::new(
                                 Span::call_site(),
-                                "value argument must be an integer",
+                                "value argument must be an integer or a string",
                             ))
                         }
                     }
@@ -217,6 +274,9 @@ impl Parse for ArgAttributes {
                 syn::Meta::Path(path) if path.is_ident("default") => {
                     attrs.default = true;
                 }
+                syn::Meta::Path(path) if path.is_ident("other") => {
+                    attrs.other = true;
+                }
                 _ => return Err(Error::new(Span::call_site(), "unknown argument!")),
             }
 
@@ -240,7 +300,116 @@ impl Parse for UrcAttributes {
             }
         };
 
-        Ok(Self { code })
+        let mut at_urc = Self {
+            code,
+            lines: None,
+            payload_len_field: None,
+        };
+
+        while input.parse::<syn::token::Comma>().is_ok() {
+            let optional = input.parse::<syn::MetaNameValue>()?;
+            if optional.path.is_ident("lines") {
+                match optional.lit {
+                    Lit::Int(v) => match v.base10_parse()? {
+                        0 => return Err(Error::new(v.span(), "'lines' must be at least 1")),
+                        lines => at_urc.lines = Some(lines),
+                    },
+                    _ => {
+                        return Err(Error::new(
+                            input.span(),
+                            "expected integer value for 'lines'",
+                        ))
+                    }
+                }
+            } else if optional.path.is_ident("payload_len_field") {
+                match optional.lit {
+                    Lit::Int(v) => at_urc.payload_len_field = Some(v.base10_parse()?),
+                    _ => {
+                        return Err(Error::new(
+                            input.span(),
+                            "expected integer value for 'payload_len_field'",
+                        ))
+                    }
+                }
+            } else {
+                return Err(Error::new(input.span(), "unknown argument!"));
+            }
+        }
+
+        if at_urc.lines.is_some() && at_urc.payload_len_field.is_some() {
+            return Err(Error::new(
+                input.span(),
+                "'lines' and 'payload_len_field' cannot be combined",
+            ));
+        }
+
+        Ok(at_urc)
+    }
+}
+
+impl Parse for EnumAttributes {
+    fn parse(input: ParseStream) -> Result<Self> {
+        let mut attrs = Self::default();
+
+        while {
+            if input.peek2(syn::token::Eq) {
+                let optional = input.parse::<syn::MetaNameValue>()?;
+                match optional.lit {
+                    Lit::Bool(v) if optional.path.is_ident("quoted") => {
+                        attrs.quoted = v.value;
+                    }
+                    _ if optional.path.is_ident("quoted") => {
+                        return Err(Error::new(
+                            Span::call_site(),
+                            "expected bool value for 'quoted'",
+                        ))
+                    }
+                    _ => return Err(Error::new(Span::call_site(), "unknown argument!")),
+                }
+            } else {
+                attrs.repr = input.parse()?;
+            }
+
+            input.parse::<syn::token::Comma>().is_ok()
+        } {}
+
+        Ok(attrs)
+    }
+}
+
+impl Parse for RespAttributes {
+    fn parse(input: ParseStream) -> Result<Self> {
+        let mut attrs = Self::default();
+
+        while !input.is_empty() {
+            if input.peek2(syn::token::Eq) {
+                let optional = input.parse::<syn::MetaNameValue>()?;
+                match optional.lit {
+                    Lit::Int(v) if optional.path.is_ident("max_len") => {
+                        attrs.max_len = Some(MaxLen::Fixed(v.base10_parse()?));
+                    }
+                    _ if optional.path.is_ident("max_len") => {
+                        return Err(Error::new(
+                            Span::call_site(),
+                            "expected integer value for 'max_len'",
+                        ))
+                    }
+                    _ => return Err(Error::new(Span::call_site(), "unknown argument!")),
+                }
+            } else {
+                let ident = input.parse::<Ident>()?;
+                if ident != "max_len" {
+                    return Err(Error::new(ident.span(), "unknown argument!"));
+                }
+                attrs.max_len = Some(MaxLen::Fields);
+            }
+
+            if input.parse::<syn::token::Comma>().is_err() {
+                break;
+            }
+        }
+
+        Ok(attrs)
     }
 }
 
@@ -249,7 +418,7 @@ impl Parse for CmdAttributes {
         let call_site = Span::call_site();
         let cmd = input.parse::<syn::LitStr>()?;
         let _comma = input.parse::<syn::token::Comma>()?;
-        let response_ident = input.parse::<Path>()?;
+        let response_ident = input.parse::<Type>()?;
 
         let mut at_cmd = Self {
             cmd: cmd.value(),
@@ -261,9 +430,26 @@ impl Parse for CmdAttributes {
             value_sep: true,
             cmd_prefix: String::from("AT"),
             termination: String::from("\r\n"),
+            read: None,
+            test: None,
         };
 
         while input.parse::<syn::token::Comma>().is_ok() {
+            // `read` and `test` take a response type rather than a literal
+            if let Ok(ident) = input.fork().parse::<Ident>() {
+                if ident == "read" || ident == "test" {
+                    input.parse::<Ident>()?;
+                    input.parse::<syn::token::Eq>()?;
+                    let resp = Some(input.parse::<Type>()?);
+                    if ident == "read" {
+                        at_cmd.read = resp;
+                    } else {
+                        at_cmd.test = resp;
+                    }
+                    continue;
+                }
+            }
+
             let optional = input.parse::<syn::MetaNameValue>()?;
             if optional.path.is_ident("timeout_ms") {
                 match optional.lit {
@@ -347,6 +533,7 @@ impl Parse for ParseInput {
 
         let mut at_cmd = None;
         let mut at_enum = None;
+        let mut at_resp = None;
 
         // Parse valid container attributes
         for attr in derive_input.attrs {
@@ -358,22 +545,29 @@ impl Parse for ParseInput {
                 }
                 at_cmd = Some(at_cmd_arg.parse2(attr.tokens)?);
             } else if attr.path.is_ident("at_enum") {
-                fn at_enum_arg(input: ParseStream) -> Result<Ident> {
+                fn at_enum_arg(input: ParseStream) -> Result<EnumAttributes> {
+                    let content;
+                    parenthesized!(content in input);
+                    content.parse()
+                }
+                at_enum = Some(at_enum_arg.parse2(attr.tokens)?);
+            } else if attr.path.is_ident("at_resp") {
+                fn at_resp_arg(input: ParseStream) -> Result<RespAttributes> {
                     let content;
                     parenthesized!(content in input);
                     content.parse()
                 }
-                at_enum = Some(EnumAttributes {
-                    repr: at_enum_arg.parse2(attr.tokens)?,
-                });
+                at_resp = Some(at_resp_arg.parse2(attr.tokens)?);
             }
         }
 
         Ok(Self {
             ident: derive_input.ident,
+            vis: derive_input.vis,
             generics: derive_input.generics,
             at_cmd,
             at_enum,
+            at_resp,
             variants: sorted_variants(derive_input.data)?,
         })
     }
@@ -2,13 +2,17 @@ use proc_macro::TokenStream;
 use quote::quote;
 use syn::parse_macro_input;
 
-use crate::{helpers, parse::ParseInput};
+use crate::{
+    helpers,
+    parse::{MaxLen, ParseInput, RespAttributes},
+};
 
 pub fn atat_resp(input: TokenStream) -> TokenStream {
     let ParseInput {
         ident,
         generics,
         variants,
+        at_resp,
         ..
     } = parse_macro_input!(input as ParseInput);
 
@@ -19,9 +23,24 @@ pub fn atat_resp(input: TokenStream) -> TokenStream {
 
     let deserialize_struct = helpers::deserialize_struct(&ident, &variants, &generics);
 
+    // Only calculated on request, as it requires all fields to implement
+    // `AtatLen`
+    let max_len = match at_resp.and_then(|RespAttributes { max_len }| max_len) {
+        Some(MaxLen::Fields) => {
+            // Fields are separated by a single comma
+            let n_fields = variants.len();
+            let len = crate::len::struct_len(variants, n_fields.saturating_sub(1));
+            quote! { const MAX_LEN: Option<usize> = Some(#len); }
+        }
+        Some(MaxLen::Fixed(len)) => quote! { const MAX_LEN: Option<usize> = Some(#len); },
+        None => quote! {},
+    };
+
     TokenStream::from(quote! {
         #[automatically_derived]
-        impl #impl_generics atat::AtatResp for #ident #ty_generics #where_clause {}
+        impl #impl_generics atat::AtatResp for #ident #ty_generics #where_clause {
+            #max_len
+        }
 
         #[automatically_derived]
         impl #serde_impl_generics atat::serde_at::serde::Deserialize<'de> for #ident #ty_generics #where_clause {
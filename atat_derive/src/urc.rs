@@ -1,13 +1,14 @@
 use crate::proc_macro::TokenStream;
 
-use quote::quote;
+use quote::{format_ident, quote};
 use syn::{parse_macro_input, Fields};
 
-use crate::parse::{ParseInput, UrcAttributes};
+use crate::parse::{parse_field_attr, ArgAttributes, FieldAttributes, ParseInput, UrcAttributes};
 
 pub fn atat_urc(input: TokenStream) -> TokenStream {
     let ParseInput {
         ident,
+        vis,
         generics,
         variants,
         ..
@@ -21,7 +22,8 @@ pub fn atat_urc(input: TokenStream) -> TokenStream {
 
     let match_arms: Vec<_> = variants.iter().map(|variant| {
         let UrcAttributes {
-            code
+            code,
+            ..
         } = variant.attrs.at_urc.clone().unwrap_or_else(|| {
             panic!(
                 "missing #[at_urc(...)] attribute",
@@ -30,17 +32,52 @@ pub fn atat_urc(input: TokenStream) -> TokenStream {
 
         let variant_ident = variant.ident.clone();
         match variant.fields.clone() {
-            Some(Fields::Named(_)) => {
-                panic!("cannot handle named enum variants")
+            Some(Fields::Named(f)) => {
+                // Parse the fields as a tuple, ordered by `#[at_arg(position = ..)]`
+                let mut fields: Vec<_> = f
+                    .named
+                    .iter()
+                    .enumerate()
+                    .map(|(i, field)| {
+                        let position = match parse_field_attr(&field.attrs) {
+                            Ok(FieldAttributes {
+                                at_arg: Some(ArgAttributes { position: Some(p), .. }),
+                                ..
+                            }) => p,
+                            _ => i,
+                        };
+                        (position, field.ident.clone().unwrap(), field.ty.clone())
+                    })
+                    .collect();
+                fields.sort_by_key(|(position, ..)| *position);
+                let (field_idents, field_types): (Vec<_>, Vec<_>) =
+                    fields.into_iter().map(|(_, ident, ty)| (ident, ty)).unzip();
+
+                quote! {
+                    #code => {
+                        let (#(#field_idents,)*) = atat::serde_at::from_slice::<(#(#field_types,)*)>(&resp).ok()?;
+                        #ident::#variant_ident { #(#field_idents),* }
+                    }
+                }
             }
             Some(Fields::Unnamed(f)) => {
-                let mut field_iter = f.unnamed.iter();
-                let first_field = field_iter.next().expect("variant must have exactly one field");
-                if field_iter.next().is_some() {
-                    panic!("cannot handle variants with more than one field")
-                }
-                quote! {
-                    #code => #ident::#variant_ident(atat::serde_at::from_slice::<#first_field>(&resp).ok()?),
+                if f.unnamed.len() == 1 {
+                    let first_field = &f.unnamed[0].ty;
+                    quote! {
+                        #code => #ident::#variant_ident(atat::serde_at::from_slice::<#first_field>(&resp).ok()?),
+                    }
+                } else {
+                    // Parse the fields as a tuple
+                    let field_types: Vec<_> = f.unnamed.iter().map(|field| field.ty.clone()).collect();
+                    let field_idents: Vec<_> = (0..field_types.len())
+                        .map(|i| format_ident!("__field{}", i))
+                        .collect();
+                    quote! {
+                        #code => {
+                            let (#(#field_idents,)*) = atat::serde_at::from_slice::<(#(#field_types,)*)>(&resp).ok()?;
+                            #ident::#variant_ident(#(#field_idents),*)
+                        }
+                    }
                 }
             }
             Some(Fields::Unit) => {
@@ -54,23 +91,87 @@ pub fn atat_urc(input: TokenStream) -> TokenStream {
         }
     }).collect();
 
+    // URCs that cannot be captured by the digester's line based URC handling
+    let matcher_arms: Vec<_> = variants
+        .iter()
+        .filter_map(|variant| {
+            let UrcAttributes {
+                code,
+                lines,
+                payload_len_field,
+            } = variant.attrs.at_urc.clone()?;
+            let code_len = code.value().len();
+            let matcher = match (lines, payload_len_field) {
+                (Some(lines), _) => quote! { atat::urc_matcher::match_lines(buf, #lines) },
+                (_, Some(field)) => {
+                    quote! { atat::urc_matcher::match_payload_len(buf, #field) }
+                }
+                _ => return None,
+            };
+            Some(quote! {
+                if buf.starts_with(#code) && matches!(buf.get(#code_len), None | Some(b':')) {
+                    return #matcher;
+                }
+            })
+        })
+        .collect();
+
+    // Always generated, so `<Ident>::matcher()` can be used regardless of the
+    // variant attributes. Without any arms, every URC is left to the digester
+    let matcher_ident = format_ident!("{}UrcMatcher", ident);
+    let doc = format!(
+        "[`UrcMatcher`](atat::UrcMatcher) for the multi-line and length-prefixed URCs of [`{}`]",
+        ident
+    );
+    let matcher = quote! {
+        #[doc = #doc]
+        #[derive(Debug, Default)]
+        #vis struct #matcher_ident;
+
+        #[automatically_derived]
+        impl atat::UrcMatcher for #matcher_ident {
+            #[allow(unused_variables)]
+            fn process<const L: usize>(
+                &mut self,
+                buf: &mut atat::heapless::Vec<u8, L>,
+            ) -> atat::UrcMatcherResult<L> {
+                #(#matcher_arms)*
+                atat::UrcMatcherResult::NotHandled
+            }
+        }
+
+        #[automatically_derived]
+        impl #impl_generics #ident #ty_generics #where_clause {
+            /// URC matcher for use with `ClientBuilder::with_custom_urc_matcher`
+            #vis fn matcher() -> #matcher_ident {
+                #matcher_ident
+            }
+        }
+    };
+
     TokenStream::from(quote! {
+        #matcher
+
         #[automatically_derived]
         impl #impl_generics atat::AtatUrc for #ident #ty_generics #where_clause {
             type Response = #ident;
 
             #[inline]
             fn parse(resp: &[u8]) -> Option<Self::Response> {
-                if let Some(index) = resp.iter().position(|&x| x == b':') {
-                    Some(match &resp[..index] {
-                        #(
-                            #match_arms
-                        )*
-                        _ => return None
-                    })
-                } else {
-                    None
-                }
+                // Match on the code before the ':', or the whole line for
+                // URCs without parameters, eg. `RING`
+                let code = match resp.iter().position(|&x| x == b':') {
+                    Some(index) => &resp[..index],
+                    None => resp,
+                };
+                let code = atat::helpers::SliceExt::trim(code, b" \t\r\n");
+
+                Some(match code {
+                    #(
+                        #match_arms
+                    )*
+                    _ => return None
+                })
             }
         }
     })
@@ -0,0 +1,78 @@
+//! Parse arbitrary responses and URCs into representative derived types.
+//!
+//! Checks that deserializing never panics, whatever the input.
+#![no_main]
+#![allow(dead_code)]
+
+use atat::atat_derive::{AtatEnum, AtatResp, AtatUrc};
+use atat::heapless::{String, Vec};
+use atat::serde_at::{from_slice, CharVec, RangeList};
+use atat::AtatUrc as _;
+use libfuzzer_sys::fuzz_target;
+
+#[derive(Debug, Clone, PartialEq, AtatEnum)]
+#[at_enum(u8)]
+enum Functionality {
+    #[at_arg(value = 0)]
+    Min,
+    #[at_arg(value = 1)]
+    Full,
+    #[at_arg(value = 4)]
+    APM,
+}
+
+#[derive(Debug, Clone, AtatResp)]
+struct SocketData {
+    #[at_arg(position = 0)]
+    socket: u8,
+    #[at_arg(position = 1)]
+    length: usize,
+    #[at_arg(position = 2)]
+    data: String<64>,
+}
+
+#[derive(Debug, Clone, AtatResp)]
+struct Mixed {
+    #[at_arg(position = 0)]
+    fun: Functionality,
+    #[at_arg(position = 1)]
+    rst: Option<u8>,
+    #[at_arg(position = 2)]
+    level: i16,
+    #[at_arg(position = 3)]
+    bytes: Vec<u8, 16>,
+}
+
+#[derive(Debug, Clone, AtatResp)]
+struct Ranges {
+    fun: RangeList<u8, 4>,
+    rst: RangeList<u8, 2>,
+}
+
+#[derive(Debug, Clone, AtatResp)]
+struct Tuple(u8, CharVec<8>, bool, f32);
+
+#[derive(Debug, Clone, AtatUrc)]
+enum Urc {
+    #[at_urc(b"+UUSORD")]
+    SocketDataAvailable {
+        #[at_arg(position = 0)]
+        socket: u8,
+        #[at_arg(position = 1)]
+        length: usize,
+    },
+    #[at_urc(b"+CREG")]
+    NetworkRegistration(u8, Option<String<8>>),
+    #[at_urc(b"RING")]
+    Ring,
+}
+
+fuzz_target!(|data: &[u8]| {
+    let _ = from_slice::<SocketData>(data);
+    let _ = from_slice::<Mixed>(data);
+    let _ = from_slice::<Ranges>(data);
+    let _ = from_slice::<Tuple>(data);
+    let _ = from_slice::<(u8, String<16>)>(data);
+    let _ = from_slice::<Vec<(u8, String<8>), 4>>(data);
+    let _ = Urc::parse(data);
+});
@@ -0,0 +1,104 @@
+//! Feed arbitrarily chunked byte streams through an `IngressManager`, with the
+//! default digester and URC matcher.
+//!
+//! Checks that digesting never panics, that the buffer stays bounded, and that
+//! every received byte is either consumed or reported, with nothing left in
+//! the buffer once the line falls silent.
+#![no_main]
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use arbitrary::Arbitrary;
+use atat::clock::Instant;
+use atat::heapless::spsc::Queue;
+use atat::observer::{Event, Observer};
+use atat::{ComQueue, IngressManager, ResQueue, UrcQueue};
+use libfuzzer_sys::fuzz_target;
+
+const BUF_LEN: usize = 256;
+const URC_CAPACITY: usize = 4;
+const SILENCE_TIMEOUT: u32 = 50;
+
+#[derive(Debug, Arbitrary)]
+struct Chunk {
+    data: Vec<u8>,
+    /// Milliseconds since the previous chunk
+    delay: u8,
+}
+
+/// Observer counting received and reported bytes
+struct Counter {
+    received: AtomicUsize,
+    reported: AtomicUsize,
+}
+
+impl Observer for Counter {
+    fn on_event(&self, event: &Event<'_>) {
+        let reported = match event {
+            Event::DataReceived { bytes, .. } => {
+                self.received.fetch_add(bytes.len(), Ordering::Relaxed);
+                return;
+            }
+            Event::EchoReceived { len, .. } => *len,
+            Event::Response {
+                result: Ok(bytes), ..
+            }
+            | Event::UrcReceived { bytes, .. }
+            | Event::Overflow { bytes, .. }
+            | Event::BufferCleared { bytes, .. } => bytes.len(),
+            _ => 0,
+        };
+        self.reported.fetch_add(reported, Ordering::Relaxed);
+    }
+}
+
+static COUNTER: Counter = Counter {
+    received: AtomicUsize::new(0),
+    reported: AtomicUsize::new(0),
+};
+
+static mut RES_Q: ResQueue<BUF_LEN> = Queue::new();
+static mut URC_Q: UrcQueue<BUF_LEN, URC_CAPACITY> = Queue::new();
+static mut COM_Q: ComQueue = Queue::new();
+
+fuzz_target!(|chunks: Vec<Chunk>| {
+    COUNTER.received.store(0, Ordering::Relaxed);
+    COUNTER.reported.store(0, Ordering::Relaxed);
+
+    let (res_p, mut res_c) = unsafe {
+        RES_Q = Queue::new();
+        RES_Q.split()
+    };
+    let (urc_p, mut urc_c) = unsafe {
+        URC_Q = Queue::new();
+        URC_Q.split()
+    };
+    let (_com_p, com_c) = unsafe {
+        COM_Q = Queue::new();
+        COM_Q.split()
+    };
+
+    let mut ingress = IngressManager::new(res_p, urc_p, com_c)
+        .with_silence_timeout(SILENCE_TIMEOUT)
+        .with_reset_markers(&[b"RDY"])
+        .with_observer(&COUNTER);
+
+    let mut now = Instant::from_millis(0);
+    for chunk in &chunks {
+        now = now.add_millis(u32::from(chunk.delay));
+        ingress.write_at(&chunk.data, now);
+        ingress.digest_at(now);
+        assert!(ingress.len() <= ingress.capacity());
+
+        // Act as the client, consuming everything reported
+        while urc_c.dequeue().is_some() {}
+        while res_c.dequeue().is_some() {}
+    }
+
+    // Flush whatever is left after a silence
+    ingress.digest_at(now.add_millis(SILENCE_TIMEOUT + 1));
+    assert!(ingress.is_empty());
+
+    // Bytes are never reported twice
+    assert!(COUNTER.reported.load(Ordering::Relaxed) <= COUNTER.received.load(Ordering::Relaxed));
+});
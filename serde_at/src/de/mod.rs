@@ -10,10 +10,14 @@ use serde::{
 
 use self::enum_::VariantAccess;
 use self::map::MapAccess;
-use self::seq::{SeqAccess, SeqByteAccess};
+use self::range::{RANGE_LIST_TOKEN, RANGE_TOKEN};
+use self::seq::{ParenSeqAccess, RangeAccess, SeqAccess, SeqByteAccess};
+
+pub use self::range::RangeList;
 
 mod enum_;
 mod map;
+mod range;
 mod seq;
 
 /// Deserialization result
@@ -142,6 +146,12 @@ pub enum Error {
     /// AT Command string has a comma after the last value in an array or map.
     TrailingComma,
 
+    /// Expected a `)` to close a parenthesised list.
+    ExpectedListEnd,
+
+    /// The deserialized type is not supported by the AT Command format.
+    Unsupported,
+
     /// Error with a custom message that we had to discard.
     CustomError,
 
@@ -207,18 +217,17 @@ impl<'a> Deserializer<'a> {
         }
     }
 
+    /// Parse an unquoted token, eg. `READY`, ending at a value separator, a
+    /// closing parenthesis or a line ending
     fn parse_bytes(&mut self) -> Result<&'a [u8]> {
         let start = self.index;
         loop {
-            if let Some(c) = self.peek() {
-                if (c as char).is_alphanumeric() || (c as char).is_whitespace() {
-                    self.eat_char()
-                } else {
-                    return Err(Error::EofWhileParsingString);
+            match self.peek() {
+                Some(b',' | b')' | b'\r' | b'\n') | None => {
+                    let end = self.index;
+                    return Ok(&self.slice[start..end]);
                 }
-            } else {
-                let end = self.index;
-                return Ok(&self.slice[start..end]);
+                Some(_) => self.eat_char(),
             }
         }
     }
@@ -248,6 +257,23 @@ impl<'a> Deserializer<'a> {
         Ok(None)
     }
 
+    /// Parse a comma separated list of values enclosed in parentheses, eg.
+    /// `(0,1,4)`
+    fn deserialize_parenthesised<V>(&mut self, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'a>,
+    {
+        self.eat_char();
+        let value = visitor.visit_seq(ParenSeqAccess::new(self))?;
+        match self.parse_whitespace() {
+            Some(b')') => {
+                self.eat_char();
+                Ok(value)
+            }
+            _ => Err(Error::ExpectedListEnd),
+        }
+    }
+
     /// Consumes all the whitespace characters and returns a peek into the next character
     fn parse_whitespace(&mut self) -> Option<u8> {
         loop {
@@ -377,7 +403,7 @@ impl<'a, 'de> de::Deserializer<'de> for &'a mut Deserializer<'de> {
     where
         V: Visitor<'de>,
     {
-        unreachable!()
+        Err(Error::Unsupported)
     }
 
     fn deserialize_bool<V>(self, visitor: V) -> Result<V::Value>
@@ -492,6 +518,12 @@ impl<'a, 'de> de::Deserializer<'de> for &'a mut Deserializer<'de> {
     {
         let peek = self.parse_whitespace().ok_or(Error::EofWhileParsingValue)?;
         self.eat_char();
+        // Chars are serialized quoted, eg. `"a"`
+        if peek == b'"' && self.slice.get(self.index + 1) == Some(&b'"') {
+            let c = self.slice[self.index];
+            self.index += 2;
+            return visitor.visit_char(c as char);
+        }
         visitor.visit_char(peek as char)
     }
 
@@ -521,7 +553,7 @@ impl<'a, 'de> de::Deserializer<'de> for &'a mut Deserializer<'de> {
     where
         V: Visitor<'de>,
     {
-        unreachable!()
+        Err(Error::Unsupported)
     }
 
     fn deserialize_bytes<V>(self, visitor: V) -> Result<V::Value>
@@ -537,7 +569,7 @@ impl<'a, 'de> de::Deserializer<'de> for &'a mut Deserializer<'de> {
     where
         V: Visitor<'de>,
     {
-        unreachable!()
+        Err(Error::Unsupported)
     }
 
     fn deserialize_option<V>(self, visitor: V) -> Result<V::Value>
@@ -545,7 +577,7 @@ impl<'a, 'de> de::Deserializer<'de> for &'a mut Deserializer<'de> {
         V: Visitor<'de>,
     {
         match self.parse_whitespace() {
-            Some(b'+') | None => visitor.visit_none(),
+            Some(b'+' | b',' | b')') | None => visitor.visit_none(),
             Some(_) => visitor.visit_some(self),
         }
     }
@@ -555,7 +587,7 @@ impl<'a, 'de> de::Deserializer<'de> for &'a mut Deserializer<'de> {
     where
         V: Visitor<'de>,
     {
-        unreachable!()
+        Err(Error::Unsupported)
     }
 
     /// Unsupported. Use a more specific deserialize_* method
@@ -563,7 +595,7 @@ impl<'a, 'de> de::Deserializer<'de> for &'a mut Deserializer<'de> {
     where
         V: Visitor<'de>,
     {
-        unreachable!()
+        Err(Error::Unsupported)
     }
 
     fn deserialize_newtype_struct<V>(self, _name: &'static str, visitor: V) -> Result<V::Value>
@@ -578,28 +610,40 @@ impl<'a, 'de> de::Deserializer<'de> for &'a mut Deserializer<'de> {
     where
         V: Visitor<'de>,
     {
-        visitor.visit_seq(SeqAccess::new(self))
+        visitor.visit_seq(SeqAccess::list(self))
     }
 
-    /// Unsupported
-    fn deserialize_tuple<V>(self, _len: usize, _visitor: V) -> Result<V::Value>
+    /// Tuples are parsed the same way as structs, as a comma separated
+    /// sequence of values, optionally preceded by an AT command identifier.
+    ///
+    /// Tuples enclosed in parentheses, eg. `(1,"Op",7)`, are parsed as a
+    /// single value.
+    fn deserialize_tuple<V>(self, _len: usize, visitor: V) -> Result<V::Value>
     where
         V: Visitor<'de>,
     {
-        unreachable!()
+        self.deserialize_tuple_struct("", 0, visitor)
     }
 
-    /// Unsupported
     fn deserialize_tuple_struct<V>(
         self,
-        _name: &'static str,
+        name: &'static str,
         _len: usize,
-        _visitor: V,
+        visitor: V,
     ) -> Result<V::Value>
     where
         V: Visitor<'de>,
     {
-        unreachable!()
+        if name == RANGE_TOKEN {
+            self.parse_whitespace().ok_or(Error::EofWhileParsingValue)?;
+            return visitor.visit_seq(RangeAccess::new(self));
+        }
+
+        match self.parse_whitespace() {
+            Some(b'(') => self.deserialize_parenthesised(visitor),
+            _ if name == RANGE_LIST_TOKEN => Err(Error::ExpectedSomeValue),
+            _ => self.deserialize_struct(name, &[], visitor),
+        }
     }
 
     fn deserialize_map<V>(self, visitor: V) -> Result<V::Value>
@@ -629,7 +673,7 @@ impl<'a, 'de> de::Deserializer<'de> for &'a mut Deserializer<'de> {
         if self.index == self.slice.len() && self.index > 0 {
             return Err(Error::EofWhileParsingObject);
         }
-        self.deserialize_seq(visitor)
+        visitor.visit_seq(SeqAccess::new(self))
     }
 
     fn deserialize_enum<V>(
@@ -645,11 +689,17 @@ impl<'a, 'de> de::Deserializer<'de> for &'a mut Deserializer<'de> {
         visitor.visit_enum(VariantAccess::new(self))
     }
 
+    /// Like `deserialize_str`, but accepting any unquoted token, eg. string
+    /// enum values starting with a digit like `3GPP`
     fn deserialize_identifier<V>(self, visitor: V) -> Result<V::Value>
     where
         V: Visitor<'de>,
     {
-        self.deserialize_str(visitor)
+        match self.parse_whitespace().ok_or(Error::EofWhileParsingValue)? {
+            b'"' => self.deserialize_str(visitor),
+            b',' | b')' | b'\r' | b'\n' => Err(Error::InvalidType),
+            _ => visitor.visit_bytes(self.parse_bytes()?),
+        }
     }
 
     fn deserialize_ignored_any<V>(self, visitor: V) -> Result<V::Value>
@@ -715,6 +765,8 @@ impl fmt::Display for Error {
                      the \
                      value."
                 }
+                Self::ExpectedListEnd => "Expected a closing parenthesis.",
+                Self::Unsupported => "Type not supported by the AT Command format.",
                 Self::CustomError =>
                     "AT Command string does not match deserializer\u{2019}s expected format.",
                 #[cfg(feature = "custom-error-messages")]
@@ -749,7 +801,7 @@ where
 
 #[cfg(test)]
 mod tests {
-    use super::CharVec;
+    use super::{CharVec, Error, RangeList};
     use heapless::String;
     use serde_derive::Deserialize;
 
@@ -778,6 +830,95 @@ mod tests {
     #[derive(Clone, Debug, PartialEq, Deserialize)]
     struct CharHandle(pub char);
 
+    #[derive(Clone, Debug, PartialEq, Deserialize)]
+    struct TupleStruct(u8, String<16>, Option<u8>);
+
+    #[test]
+    fn tuple() {
+        assert_eq!(
+            crate::from_str("+CFG: 2,\"abc\",5"),
+            Ok(TupleStruct(2, String::from("abc"), Some(5)))
+        );
+        assert_eq!(
+            crate::from_str("+CFG: 2,\"abc\""),
+            Ok(TupleStruct(2, String::from("abc"), None))
+        );
+        assert_eq!(
+            crate::from_str::<(u8, String<16>)>("+CFG: 2,\"abc\""),
+            Ok((2, String::from("abc")))
+        );
+        assert_eq!(crate::from_str::<(u8, i16)>("7,-8"), Ok((7, -8)));
+    }
+
+    #[derive(Clone, Debug, PartialEq, Deserialize)]
+    struct Operator(u8, String<16>, String<16>, String<8>, Option<u8>);
+
+    #[derive(Clone, Debug, PartialEq, Deserialize)]
+    struct OperatorSelectionTest {
+        operators: heapless::Vec<Operator, 4>,
+        modes: RangeList<u8, 2>,
+        formats: RangeList<u8, 2>,
+    }
+
+    #[test]
+    fn range_list() {
+        assert_eq!(
+            crate::from_str::<(RangeList<u8, 4>, RangeList<u8, 4>)>("+CFUN: (0,1,4),(0-1)"),
+            Ok((
+                RangeList(heapless::Vec::from_slice(&[0..=0, 1..=1, 4..=4]).unwrap()),
+                RangeList(heapless::Vec::from_slice(&[0..=1]).unwrap())
+            ))
+        );
+        assert_eq!(
+            crate::from_str::<RangeList<i16, 4>>("( -5 - 5, 10 )"),
+            Ok(RangeList(
+                heapless::Vec::from_slice(&[-5..=5, 10..=10]).unwrap()
+            ))
+        );
+        assert_eq!(
+            crate::from_str::<RangeList<u8, 4>>("(0-4"),
+            Err(Error::EofWhileParsingValue)
+        );
+        assert_eq!(
+            crate::from_str::<RangeList<u8, 4>>("(0-4;5)"),
+            Err(Error::ExpectedListEnd)
+        );
+        assert_eq!(
+            crate::from_str::<RangeList<u8, 1>>("(0,1)"),
+            Err(Error::CustomError)
+        );
+    }
+
+    #[test]
+    fn parenthesised_list() {
+        assert_eq!(
+            crate::from_str(
+                "+COPS: (1,\"Op\",\"Op\",\"26201\",7),(2,\"Other\",\"O\",\"26202\",),,(0-4),(0-2)"
+            ),
+            Ok(OperatorSelectionTest {
+                operators: heapless::Vec::from_slice(&[
+                    Operator(
+                        1,
+                        String::from("Op"),
+                        String::from("Op"),
+                        String::from("26201"),
+                        Some(7)
+                    ),
+                    Operator(
+                        2,
+                        String::from("Other"),
+                        String::from("O"),
+                        String::from("26202"),
+                        None
+                    ),
+                ])
+                .unwrap(),
+                modes: RangeList(heapless::Vec::from_slice(&[0..=4]).unwrap()),
+                formats: RangeList(heapless::Vec::from_slice(&[0..=2]).unwrap()),
+            })
+        );
+    }
+
     #[test]
     fn simple_struct() {
         assert_eq!(
@@ -861,6 +1002,8 @@ mod tests {
     #[test]
     fn char_test() {
         assert_eq!(crate::from_str("+CCID: B"), Ok(CharHandle('B')));
+        assert_eq!(crate::from_str("+CCID: \"B\""), Ok(CharHandle('B')));
+        assert_eq!(crate::from_str("+CCID: \"\"\""), Ok(CharHandle('"')));
     }
 
     #[test]
@@ -880,4 +1023,13 @@ mod tests {
 
         assert_eq!(res.to_string(), String::<4>::from("IMP_"));
     }
+
+    #[test]
+    fn unsupported_type() {
+        #[derive(Debug, Deserialize, PartialEq)]
+        struct Unit;
+
+        assert_eq!(crate::from_str::<()>("+CCID: 1"), Err(Error::Unsupported));
+        assert_eq!(crate::from_str::<Unit>("OK"), Err(Error::Unsupported));
+    }
 }
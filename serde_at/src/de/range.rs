@@ -0,0 +1,147 @@
+use core::fmt;
+use core::marker::PhantomData;
+use core::ops::RangeInclusive;
+
+use serde::de::{self, Deserialize};
+
+/// Magic name used to request a parenthesised list of ranges from the
+/// deserializer
+pub(crate) const RANGE_LIST_TOKEN: &str = "$serde_at::private::RangeList";
+
+/// Magic name used to request a single value or range, eg. `0-4`, from the
+/// deserializer
+pub(crate) const RANGE_TOKEN: &str = "$serde_at::private::Range";
+
+/// Wrapper type to allow deserializing a parenthesised list of values and
+/// ranges, as found in the responses to test commands (`AT+CMD=?`).
+///
+/// Single values are represented as ranges containing only that value, eg.
+/// `(0,1,4)` is deserialized as `[0..=0, 1..=1, 4..=4]`.
+///
+/// Example:
+/// ```
+/// use serde_at::{from_str, RangeList};
+/// use serde_derive::Deserialize;
+///
+/// #[derive(Debug, Deserialize, PartialEq)]
+/// struct FunctionalityTest {
+///     fun: RangeList<u8, 4>,
+///     rst: RangeList<u8, 4>,
+/// }
+///
+/// let incoming: FunctionalityTest = from_str("+CFUN: (0,1,4-6),(0-1)").unwrap();
+///
+/// assert!(incoming.fun.contains(&5));
+/// assert!(!incoming.fun.contains(&2));
+/// assert_eq!(incoming.rst.as_slice(), &[0..=1]);
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct RangeList<T, const N: usize>(pub heapless::Vec<RangeInclusive<T>, N>);
+
+impl<T, const N: usize> RangeList<T, N> {
+    #[must_use]
+    pub fn new() -> Self {
+        Self(heapless::Vec::new())
+    }
+
+    #[must_use]
+    pub fn as_slice(&self) -> &[RangeInclusive<T>] {
+        &self.0
+    }
+
+    /// Check whether `value` is contained in any of the ranges
+    pub fn contains(&self, value: &T) -> bool
+    where
+        T: PartialOrd,
+    {
+        self.0.iter().any(|r| r.contains(value))
+    }
+}
+
+impl<T, const N: usize> Default for RangeList<T, N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<'de, T, const N: usize> Deserialize<'de> for RangeList<T, N>
+where
+    T: Deserialize<'de> + Clone,
+{
+    fn deserialize<D>(deserializer: D) -> core::result::Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        struct ValueVisitor<'de, T, const U: usize>(PhantomData<(&'de (), T)>);
+
+        impl<'de, T, const N: usize> de::Visitor<'de> for ValueVisitor<'de, T, N>
+        where
+            T: Deserialize<'de> + Clone,
+        {
+            type Value = RangeList<T, N>;
+
+            fn expecting(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+                formatter.write_str("a parenthesised list of ranges")
+            }
+
+            fn visit_seq<A>(self, mut seq: A) -> core::result::Result<Self::Value, A::Error>
+            where
+                A: de::SeqAccess<'de>,
+            {
+                let mut values = heapless::Vec::new();
+
+                while let Some(Range(value)) = seq.next_element()? {
+                    if values.push(value).is_err() {
+                        return Err(<A::Error as de::Error>::invalid_length(
+                            values.capacity() + 1,
+                            &self,
+                        ));
+                    }
+                }
+
+                Ok(RangeList(values))
+            }
+        }
+
+        deserializer.deserialize_tuple_struct(RANGE_LIST_TOKEN, N, ValueVisitor(PhantomData))
+    }
+}
+
+/// A single range, eg. `0-4`, or a single value, eg. `2`
+struct Range<T>(RangeInclusive<T>);
+
+impl<'de, T> Deserialize<'de> for Range<T>
+where
+    T: Deserialize<'de> + Clone,
+{
+    fn deserialize<D>(deserializer: D) -> core::result::Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        struct ValueVisitor<'de, T>(PhantomData<(&'de (), T)>);
+
+        impl<'de, T> de::Visitor<'de> for ValueVisitor<'de, T>
+        where
+            T: Deserialize<'de> + Clone,
+        {
+            type Value = Range<T>;
+
+            fn expecting(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+                formatter.write_str("a value or a range")
+            }
+
+            fn visit_seq<A>(self, mut seq: A) -> core::result::Result<Self::Value, A::Error>
+            where
+                A: de::SeqAccess<'de>,
+            {
+                let start: T = seq
+                    .next_element()?
+                    .ok_or_else(|| <A::Error as de::Error>::invalid_length(0, &self))?;
+                let end = seq.next_element()?.unwrap_or_else(|| start.clone());
+                Ok(Range(start..=end))
+            }
+        }
+
+        deserializer.deserialize_tuple_struct(RANGE_TOKEN, 2, ValueVisitor(PhantomData))
+    }
+}
@@ -5,12 +5,27 @@ use crate::de::{Deserializer, Error, Result};
 #[allow(clippy::module_name_repetitions)]
 pub struct SeqAccess<'a, 'b> {
     first: bool,
+    list: bool,
     de: &'a mut Deserializer<'b>,
 }
 
 impl<'a, 'b> SeqAccess<'a, 'b> {
     pub(crate) fn new(de: &'a mut Deserializer<'b>) -> Self {
-        SeqAccess { de, first: true }
+        SeqAccess {
+            de,
+            first: true,
+            list: false,
+        }
+    }
+
+    /// Access to a list of values, eg. a `Vec`, which unlike the fields of a
+    /// struct is terminated by an empty value, eg. `1,2,,(0-4)`
+    pub(crate) fn list(de: &'a mut Deserializer<'b>) -> Self {
+        SeqAccess {
+            de,
+            first: true,
+            list: true,
+        }
     }
 }
 
@@ -24,10 +39,15 @@ impl<'a, 'de> de::SeqAccess<'de> for SeqAccess<'a, 'de> {
         match self.de.parse_whitespace() {
             Some(b',') => {
                 self.de.eat_char();
-                self.de
+                let next = self
+                    .de
                     .parse_whitespace()
                     .ok_or(Error::EofWhileParsingValue)?;
+                if self.list && next == b',' {
+                    return Ok(None);
+                }
             }
+            Some(b')') if self.list => return Ok(None),
             Some(c) => {
                 if self.first {
                     self.first = false;
@@ -51,6 +71,74 @@ impl<'a, 'de> de::SeqAccess<'de> for SeqAccess<'a, 'de> {
     }
 }
 
+/// Access to the values of a parenthesised list, eg. `(0,1,4)`. The opening
+/// parenthesis is expected to be consumed already, and the closing
+/// parenthesis is left for the caller to consume.
+pub struct ParenSeqAccess<'a, 'b> {
+    first: bool,
+    de: &'a mut Deserializer<'b>,
+}
+
+impl<'a, 'b> ParenSeqAccess<'a, 'b> {
+    pub(crate) fn new(de: &'a mut Deserializer<'b>) -> Self {
+        ParenSeqAccess { de, first: true }
+    }
+}
+
+impl<'a, 'de> de::SeqAccess<'de> for ParenSeqAccess<'a, 'de> {
+    type Error = Error;
+
+    fn next_element_seed<T>(&mut self, seed: T) -> Result<Option<T::Value>>
+    where
+        T: de::DeserializeSeed<'de>,
+    {
+        match self.de.parse_whitespace() {
+            Some(b')') => return Ok(None),
+            Some(b',') if !self.first => {
+                self.de.eat_char();
+            }
+            Some(_) if !self.first => return Err(Error::ExpectedListEnd),
+            Some(_) => {}
+            None => return Err(Error::EofWhileParsingValue),
+        }
+        self.first = false;
+
+        Ok(Some(seed.deserialize(&mut *self.de)?))
+    }
+}
+
+/// Access to the bounds of a range, eg. `0-4`, or a single value, eg. `2`.
+/// The upper bound is only yielded if present.
+pub struct RangeAccess<'a, 'b> {
+    first: bool,
+    de: &'a mut Deserializer<'b>,
+}
+
+impl<'a, 'b> RangeAccess<'a, 'b> {
+    pub(crate) fn new(de: &'a mut Deserializer<'b>) -> Self {
+        RangeAccess { de, first: true }
+    }
+}
+
+impl<'a, 'de> de::SeqAccess<'de> for RangeAccess<'a, 'de> {
+    type Error = Error;
+
+    fn next_element_seed<T>(&mut self, seed: T) -> Result<Option<T::Value>>
+    where
+        T: de::DeserializeSeed<'de>,
+    {
+        if self.first {
+            self.first = false;
+        } else if let Some(b'-') = self.de.parse_whitespace() {
+            self.de.eat_char();
+        } else {
+            return Ok(None);
+        }
+
+        Ok(Some(seed.deserialize(&mut *self.de)?))
+    }
+}
+
 #[allow(clippy::module_name_repetitions)]
 pub struct SeqByteAccess<'a, 'b> {
     de: &'a mut Deserializer<'b>,
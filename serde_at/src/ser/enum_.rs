@@ -1,20 +1,18 @@
-use crate::ser::{Error, Result, Serializer};
+use crate::ser::{Error, Result, Serializer, Writer};
 use serde::ser;
 
-pub struct SerializeTupleVariant<'a, 'b, const B: usize, const C: usize> {
-    ser: &'a mut Serializer<'b, B, C>,
+pub struct SerializeTupleVariant<'a, 'b, W: Writer> {
+    ser: &'a mut Serializer<'b, W>,
     first: bool,
 }
 
-impl<'a, 'b, const B: usize, const C: usize> SerializeTupleVariant<'a, 'b, B, C> {
-    pub(crate) fn new(ser: &'a mut Serializer<'b, B, C>) -> Self {
+impl<'a, 'b, W: Writer> SerializeTupleVariant<'a, 'b, W> {
+    pub(crate) fn new(ser: &'a mut Serializer<'b, W>) -> Self {
         SerializeTupleVariant { ser, first: true }
     }
 }
 
-impl<'a, 'b, const B: usize, const C: usize> ser::SerializeTupleVariant
-    for SerializeTupleVariant<'a, 'b, B, C>
-{
+impl<'a, 'b, W: Writer> ser::SerializeTupleVariant for SerializeTupleVariant<'a, 'b, W> {
     type Ok = ();
     type Error = Error;
 
@@ -23,7 +21,7 @@ impl<'a, 'b, const B: usize, const C: usize> ser::SerializeTupleVariant
         T: ser::Serialize,
     {
         if !self.first {
-            self.ser.buf.push(b',')?;
+            self.ser.push_sep(b',');
         }
         self.first = false;
 
@@ -36,20 +34,34 @@ impl<'a, 'b, const B: usize, const C: usize> ser::SerializeTupleVariant
     }
 }
 
-pub struct SerializeStructVariant<'a, 'b, const B: usize, const C: usize> {
-    ser: &'a mut Serializer<'b, B, C>,
+impl<'a, 'b, W: Writer> ser::SerializeTuple for SerializeTupleVariant<'a, 'b, W> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_element<T: ?Sized>(&mut self, value: &T) -> Result<()>
+    where
+        T: ser::Serialize,
+    {
+        ser::SerializeTupleVariant::serialize_field(self, value)
+    }
+
+    fn end(self) -> Result<Self::Ok> {
+        ser::SerializeTupleVariant::end(self)
+    }
+}
+
+pub struct SerializeStructVariant<'a, 'b, W: Writer> {
+    ser: &'a mut Serializer<'b, W>,
     first: bool,
 }
 
-impl<'a, 'b, const B: usize, const C: usize> SerializeStructVariant<'a, 'b, B, C> {
-    pub(crate) fn new(ser: &'a mut Serializer<'b, B, C>) -> Self {
+impl<'a, 'b, W: Writer> SerializeStructVariant<'a, 'b, W> {
+    pub(crate) fn new(ser: &'a mut Serializer<'b, W>) -> Self {
         SerializeStructVariant { ser, first: true }
     }
 }
 
-impl<'a, 'b, const B: usize, const C: usize> ser::SerializeStructVariant
-    for SerializeStructVariant<'a, 'b, B, C>
-{
+impl<'a, 'b, W: Writer> ser::SerializeStructVariant for SerializeStructVariant<'a, 'b, W> {
     type Ok = ();
     type Error = Error;
 
@@ -58,7 +70,7 @@ impl<'a, 'b, const B: usize, const C: usize> ser::SerializeStructVariant
         T: ser::Serialize,
     {
         if !self.first {
-            self.ser.buf.push(b',')?;
+            self.ser.push_sep(b',');
         }
         self.first = false;
 
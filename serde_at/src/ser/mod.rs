@@ -102,6 +102,8 @@ impl<'a> Default for SerializeOptions<'a> {
 pub enum Error {
     /// Buffer is full
     BufferFull,
+    /// The underlying [`Writer`] failed to write the serialized bytes
+    Write,
 }
 
 impl From<()> for Error {
@@ -118,27 +120,108 @@ impl From<u8> for Error {
 
 impl fmt::Display for Error {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "Buffer is full")
+        match self {
+            Self::BufferFull => write!(f, "Buffer is full"),
+            Self::Write => write!(f, "Failed to write"),
+        }
     }
 }
 
 #[cfg(any(test, feature = "std"))]
 impl std::error::Error for Error {}
 
-pub(crate) struct Serializer<'a, const B: usize, const C: usize> {
-    buf: Vec<u8, B>,
-    cmd: String<C>,
+/// A sink for serialized bytes.
+///
+/// Implementing this trait allows [`to_writer`] to serialize directly into eg.
+/// a serial port, without staging the serialized value in a buffer first.
+pub trait Writer {
+    /// Write all of `bytes` to the sink
+    fn write_bytes(&mut self, bytes: &[u8]) -> Result<()>;
+}
+
+impl<const B: usize> Writer for Vec<u8, B> {
+    fn write_bytes(&mut self, bytes: &[u8]) -> Result<()> {
+        self.extend_from_slice(bytes)?;
+        Ok(())
+    }
+}
+
+impl<W: Writer + ?Sized> Writer for &mut W {
+    fn write_bytes(&mut self, bytes: &[u8]) -> Result<()> {
+        (**self).write_bytes(bytes)
+    }
+}
+
+/// Writer into a borrowed byte slice, keeping track of the number of bytes
+/// written
+struct SliceWriter<'a> {
+    buf: &'a mut [u8],
+    len: usize,
+}
+
+impl<'a> Writer for SliceWriter<'a> {
+    fn write_bytes(&mut self, bytes: &[u8]) -> Result<()> {
+        let end = self.len + bytes.len();
+        self.buf
+            .get_mut(self.len..end)
+            .ok_or(Error::BufferFull)?
+            .copy_from_slice(bytes);
+        self.len = end;
+        Ok(())
+    }
+}
+
+pub(crate) struct Serializer<'a, W> {
+    writer: W,
+    cmd: &'a str,
     options: SerializeOptions<'a>,
+    /// Separator to write before the next value. This is deferred until the
+    /// value is actually written, to allow omitting it for trailing `None`
+    /// values.
+    sep: Option<u8>,
+    /// Number of `None` values since the pending separator, written as empty
+    /// parameters if followed by a value, eg. `AT+CMD=,3`
+    skipped: usize,
 }
 
-impl<'a, const B: usize, const C: usize> Serializer<'a, B, C> {
-    fn new(cmd: String<C>, options: SerializeOptions<'a>) -> Self {
+impl<'a, W: Writer> Serializer<'a, W> {
+    fn new(writer: W, cmd: &'a str, options: SerializeOptions<'a>) -> Self {
         Serializer {
-            buf: Vec::new(),
+            writer,
             cmd,
             options,
+            sep: None,
+            skipped: 0,
+        }
+    }
+
+    /// Write `bytes`, preceded by any pending separators
+    pub(crate) fn write(&mut self, bytes: &[u8]) -> Result<()> {
+        if let Some(sep) = self.sep.take() {
+            self.writer.write_bytes(&[sep])?;
+        }
+        for _ in 0..core::mem::take(&mut self.skipped) {
+            self.writer.write_bytes(b",")?;
+        }
+        self.writer.write_bytes(bytes)
+    }
+
+    /// Set the separator to write before the next value. If a separator is
+    /// still pending, the previous value was `None`, and is kept as an empty
+    /// parameter.
+    pub(crate) fn push_sep(&mut self, sep: u8) {
+        if self.sep.is_some() {
+            self.skipped += 1;
+        } else {
+            self.sep = Some(sep);
         }
     }
+
+    /// Drop the separators of trailing `None` values
+    pub(crate) fn clear_sep(&mut self) {
+        self.sep = None;
+        self.skipped = 0;
+    }
 }
 
 // NOTE(serialize_*signed) This is basically the numtoa implementation minus the lookup tables,
@@ -159,8 +242,7 @@ macro_rules! serialize_unsigned {
             i -= 1;
         }
 
-        $self.buf.extend_from_slice(&buf[i..])?;
-        Ok(())
+        $self.write(&buf[i..])
     }};
 }
 
@@ -193,8 +275,7 @@ macro_rules! serialize_signed {
         } else {
             i += 1;
         }
-        $self.buf.extend_from_slice(&buf[i..])?;
-        Ok(())
+        $self.write(&buf[i..])
     }};
 }
 
@@ -202,30 +283,27 @@ macro_rules! serialize_fmt {
     ($self:ident, $N:expr, $fmt:expr, $v:expr) => {{
         let mut s: String<$N> = String::new();
         write!(&mut s, $fmt, $v).unwrap();
-        $self.buf.extend_from_slice(s.as_bytes())?;
-        Ok(())
+        $self.write(s.as_bytes())
     }};
 }
 
-impl<'a, 'b, const B: usize, const C: usize> ser::Serializer for &'a mut Serializer<'b, B, C> {
+impl<'a, 'b, W: Writer> ser::Serializer for &'a mut Serializer<'b, W> {
     type Ok = ();
     type Error = Error;
     type SerializeSeq = Unreachable;
-    type SerializeTuple = Unreachable;
-    type SerializeTupleStruct = Unreachable;
-    type SerializeTupleVariant = SerializeTupleVariant<'a, 'b, B, C>;
+    type SerializeTuple = SerializeTupleVariant<'a, 'b, W>;
+    type SerializeTupleStruct = SerializeStruct<'a, 'b, W>;
+    type SerializeTupleVariant = SerializeTupleVariant<'a, 'b, W>;
     type SerializeMap = Unreachable;
-    type SerializeStruct = SerializeStruct<'a, 'b, B, C>;
-    type SerializeStructVariant = SerializeStructVariant<'a, 'b, B, C>;
+    type SerializeStruct = SerializeStruct<'a, 'b, W>;
+    type SerializeStructVariant = SerializeStructVariant<'a, 'b, W>;
 
     fn serialize_bool(self, v: bool) -> Result<Self::Ok> {
         if v {
-            self.buf.extend_from_slice(b"true")?;
+            self.write(b"true")
         } else {
-            self.buf.extend_from_slice(b"false")?;
+            self.write(b"false")
         }
-
-        Ok(())
     }
 
     fn serialize_i8(self, v: i8) -> Result<Self::Ok> {
@@ -277,26 +355,22 @@ impl<'a, 'b, const B: usize, const C: usize> ser::Serializer for &'a mut Seriali
     }
 
     fn serialize_char(self, v: char) -> Result<Self::Ok> {
-        self.buf.push(b'"')?;
-        self.buf.push(v as u8)?;
-        self.buf.push(b'"')?;
-        Ok(())
+        self.write(&[b'"', v as u8, b'"'])
     }
 
     fn serialize_str(self, v: &str) -> Result<Self::Ok> {
-        self.buf.push(b'"')?;
-        self.buf.extend_from_slice(v.as_bytes())?;
-        self.buf.push(b'"')?;
-        Ok(())
+        self.write(b"\"")?;
+        self.write(v.as_bytes())?;
+        self.write(b"\"")
     }
 
     fn serialize_bytes(self, v: &[u8]) -> Result<Self::Ok> {
-        self.buf.extend_from_slice(v)?;
-        Ok(())
+        self.write(v)
     }
 
     fn serialize_none(self) -> Result<Self::Ok> {
-        self.buf.truncate(self.buf.len() - 1);
+        // Leave the separator pending, so it is only written if a value
+        // follows
         Ok(())
     }
 
@@ -308,16 +382,13 @@ impl<'a, 'b, const B: usize, const C: usize> ser::Serializer for &'a mut Seriali
     }
 
     fn serialize_unit(self) -> Result<Self::Ok> {
-        unreachable!()
+        Ok(())
     }
 
     fn serialize_unit_struct(self, _name: &'static str) -> Result<Self::Ok> {
-        self.buf
-            .extend_from_slice(self.options.cmd_prefix.as_bytes())?;
-        self.buf.extend_from_slice(self.cmd.as_bytes())?;
-        self.buf
-            .extend_from_slice(self.options.termination.as_bytes())?;
-        Ok(())
+        self.write(self.options.cmd_prefix.as_bytes())?;
+        self.write(self.cmd.as_bytes())?;
+        self.write(self.options.termination.as_bytes())
     }
 
     fn serialize_unit_variant(
@@ -347,7 +418,7 @@ impl<'a, 'b, const B: usize, const C: usize> ser::Serializer for &'a mut Seriali
         T: ser::Serialize,
     {
         self.serialize_u32(variant_index)?;
-        self.buf.push(b',')?;
+        self.push_sep(b',');
         value.serialize(self)
     }
 
@@ -356,7 +427,7 @@ impl<'a, 'b, const B: usize, const C: usize> ser::Serializer for &'a mut Seriali
     }
 
     fn serialize_tuple(self, _len: usize) -> Result<Self::SerializeTuple> {
-        unreachable!()
+        Ok(SerializeTupleVariant::new(self))
     }
 
     fn serialize_tuple_struct(
@@ -364,7 +435,9 @@ impl<'a, 'b, const B: usize, const C: usize> ser::Serializer for &'a mut Seriali
         _name: &'static str,
         _len: usize,
     ) -> Result<Self::SerializeTupleStruct> {
-        unreachable!()
+        self.write(self.options.cmd_prefix.as_bytes())?;
+        self.write(self.cmd.as_bytes())?;
+        Ok(SerializeStruct::new(self))
     }
 
     fn serialize_tuple_variant(
@@ -375,7 +448,7 @@ impl<'a, 'b, const B: usize, const C: usize> ser::Serializer for &'a mut Seriali
         _len: usize,
     ) -> Result<Self::SerializeTupleVariant> {
         self.serialize_u32(variant_index)?;
-        self.buf.push(b',')?;
+        self.push_sep(b',');
         Ok(SerializeTupleVariant::new(self))
     }
 
@@ -384,9 +457,8 @@ impl<'a, 'b, const B: usize, const C: usize> ser::Serializer for &'a mut Seriali
     }
 
     fn serialize_struct(self, _name: &'static str, _len: usize) -> Result<Self::SerializeStruct> {
-        self.buf
-            .extend_from_slice(self.options.cmd_prefix.as_bytes())?;
-        self.buf.extend_from_slice(self.cmd.as_bytes())?;
+        self.write(self.options.cmd_prefix.as_bytes())?;
+        self.write(self.cmd.as_bytes())?;
         Ok(SerializeStruct::new(self))
     }
 
@@ -398,7 +470,7 @@ impl<'a, 'b, const B: usize, const C: usize> ser::Serializer for &'a mut Seriali
         _len: usize,
     ) -> Result<Self::SerializeStructVariant> {
         self.serialize_u32(variant_index)?;
-        self.buf.push(b',')?;
+        self.push_sep(b',');
         Ok(SerializeStructVariant::new(self))
     }
 
@@ -416,10 +488,10 @@ pub fn to_string<T, const B: usize, const C: usize>(
 where
     T: ser::Serialize + ?Sized,
 {
-    let mut ser = Serializer::<B, C>::new(cmd, options);
+    let mut ser = Serializer::new(Vec::<u8, B>::new(), &cmd, options);
     value.serialize(&mut ser)?;
     Ok(String::from(unsafe {
-        core::str::from_utf8_unchecked(&ser.buf)
+        core::str::from_utf8_unchecked(&ser.writer)
     }))
 }
 
@@ -432,9 +504,36 @@ pub fn to_vec<T, const B: usize, const C: usize>(
 where
     T: ser::Serialize + ?Sized,
 {
-    let mut ser = Serializer::new(cmd, options);
+    let mut ser = Serializer::new(Vec::new(), &cmd, options);
+    value.serialize(&mut ser)?;
+    Ok(ser.writer)
+}
+
+/// Serializes the given data structure into `buf`, returning the number of
+/// bytes written
+pub fn to_slice<T>(
+    value: &T,
+    cmd: &str,
+    options: SerializeOptions<'_>,
+    buf: &mut [u8],
+) -> Result<usize>
+where
+    T: ser::Serialize + ?Sized,
+{
+    let mut ser = Serializer::new(SliceWriter { buf, len: 0 }, cmd, options);
     value.serialize(&mut ser)?;
-    Ok(ser.buf)
+    Ok(ser.writer.len)
+}
+
+/// Serializes the given data structure directly into `writer`, without
+/// staging it in an intermediate buffer
+pub fn to_writer<T, W>(value: &T, cmd: &str, options: SerializeOptions<'_>, writer: W) -> Result<()>
+where
+    T: ser::Serialize + ?Sized,
+    W: Writer,
+{
+    let mut ser = Serializer::new(writer, cmd, options);
+    value.serialize(&mut ser)
 }
 
 impl ser::Error for Error {
@@ -446,19 +545,6 @@ impl ser::Error for Error {
 #[allow(clippy::empty_enum)]
 pub(crate) enum Unreachable {}
 
-impl ser::SerializeTupleStruct for Unreachable {
-    type Ok = ();
-    type Error = Error;
-
-    fn serialize_field<T: ?Sized>(&mut self, _value: &T) -> Result<()> {
-        unreachable!()
-    }
-
-    fn end(self) -> Result<Self::Ok> {
-        unreachable!()
-    }
-}
-
 impl ser::SerializeMap for Unreachable {
     type Ok = ();
     type Error = Error;
@@ -495,19 +581,6 @@ impl ser::SerializeSeq for Unreachable {
     }
 }
 
-impl ser::SerializeTuple for Unreachable {
-    type Ok = ();
-    type Error = Error;
-
-    fn serialize_element<T: ?Sized>(&mut self, _value: &T) -> Result<()> {
-        unreachable!()
-    }
-
-    fn end(self) -> Result<Self::Ok> {
-        unreachable!()
-    }
-}
-
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -582,6 +655,112 @@ mod tests {
         assert_eq!(s, String::<32>::from("15"));
     }
 
+    #[test]
+    fn none_and_unit() {
+        let s: String<32> = to_string(
+            &Option::<u8>::None,
+            String::<32>::from(""),
+            SerializeOptions::default(),
+        )
+        .unwrap();
+        assert_eq!(s, String::<32>::from(""));
+
+        let s: String<32> =
+            to_string(&(), String::<32>::from(""), SerializeOptions::default()).unwrap();
+        assert_eq!(s, String::<32>::from(""));
+
+        #[derive(Clone, PartialEq, Serialize)]
+        pub struct WithOption {
+            a: Option<u8>,
+            b: u8,
+        }
+        let s: String<32> = to_string(
+            &WithOption { a: None, b: 3 },
+            String::<32>::from("+CMD"),
+            SerializeOptions::default(),
+        )
+        .unwrap();
+        assert_eq!(s, String::<32>::from("AT+CMD=,3\r\n"));
+
+        #[derive(Clone, PartialEq, Serialize)]
+        pub struct WithOptions {
+            a: u8,
+            b: Option<u8>,
+            c: u8,
+            d: Option<u8>,
+            e: Option<u8>,
+        }
+        let s: String<32> = to_string(
+            &WithOptions {
+                a: 1,
+                b: None,
+                c: 3,
+                d: None,
+                e: None,
+            },
+            String::<32>::from("+CMD"),
+            SerializeOptions::default(),
+        )
+        .unwrap();
+        assert_eq!(s, String::<32>::from("AT+CMD=1,,3\r\n"));
+    }
+
+    #[test]
+    fn slice_serialize() {
+        #[derive(Clone, PartialEq, Serialize)]
+        pub struct Cmd {
+            a: u8,
+            b: Option<u8>,
+            s: String<16>,
+        }
+        let cmd = Cmd {
+            a: 4,
+            b: None,
+            s: String::from("abc"),
+        };
+
+        let mut buf = [0u8; 32];
+        let len = to_slice(&cmd, "+CMD", SerializeOptions::default(), &mut buf).unwrap();
+        assert_eq!(&buf[..len], b"AT+CMD=4,,\"abc\"\r\n");
+
+        let mut buf = [0u8; 8];
+        assert!(matches!(
+            to_slice(&cmd, "+CMD", SerializeOptions::default(), &mut buf),
+            Err(Error::BufferFull)
+        ));
+    }
+
+    #[test]
+    fn writer_serialize() {
+        struct ChunkWriter {
+            chunks: usize,
+            buf: heapless::Vec<u8, 32>,
+        }
+
+        impl Writer for ChunkWriter {
+            fn write_bytes(&mut self, bytes: &[u8]) -> Result<()> {
+                self.chunks += 1;
+                self.buf.extend_from_slice(bytes)?;
+                Ok(())
+            }
+        }
+
+        let mut writer = ChunkWriter {
+            chunks: 0,
+            buf: heapless::Vec::new(),
+        };
+        to_writer(
+            &PacketSwitchedParam::QoSDelay3G(15),
+            "",
+            SerializeOptions::default(),
+            &mut writer,
+        )
+        .unwrap();
+
+        assert_eq!(&writer.buf[..], b"4,15");
+        assert!(writer.chunks > 1);
+    }
+
     #[test]
     fn byte_serialize() {
         #[derive(Clone, PartialEq, Serialize)]
@@ -619,4 +798,18 @@ mod tests {
             to_string(&b, String::<32>::from("+CMD"), SerializeOptions::default()).unwrap();
         assert_eq!(s, String::<32>::from("AT+CMD=IMP_MSG,12\r\n"));
     }
+
+    #[test]
+    fn tuple_struct_serialize() {
+        #[derive(Clone, PartialEq, Serialize)]
+        pub struct TupleCmd(u8, Option<u8>, (u8, u8));
+
+        let s: String<32> = to_string(
+            &TupleCmd(1, Some(2), (3, 4)),
+            String::<32>::from("+CMD"),
+            SerializeOptions::default(),
+        )
+        .unwrap();
+        assert_eq!(s, String::<32>::from("AT+CMD=1,2,3,4\r\n"));
+    }
 }
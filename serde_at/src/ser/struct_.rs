@@ -1,34 +1,48 @@
-use crate::ser::{Error, Result, Serializer};
+use crate::ser::{Error, Result, Serializer, Writer};
 use serde::ser;
 
 #[allow(clippy::module_name_repetitions)]
-pub struct SerializeStruct<'a, 'b, const B: usize, const C: usize> {
-    ser: &'a mut Serializer<'b, B, C>,
+pub struct SerializeStruct<'a, 'b, W: Writer> {
+    ser: &'a mut Serializer<'b, W>,
     first: bool,
 }
 
-impl<'a, 'b, const B: usize, const C: usize> SerializeStruct<'a, 'b, B, C> {
-    pub(crate) fn new(ser: &'a mut Serializer<'b, B, C>) -> Self {
+impl<'a, 'b, W: Writer> SerializeStruct<'a, 'b, W> {
+    pub(crate) fn new(ser: &'a mut Serializer<'b, W>) -> Self {
         SerializeStruct { ser, first: true }
     }
 }
 
-impl<'a, 'b, const B: usize, const C: usize> ser::SerializeStruct
-    for SerializeStruct<'a, 'b, B, C>
-{
+impl<'a, 'b, W: Writer> ser::SerializeStruct for SerializeStruct<'a, 'b, W> {
     type Ok = ();
     type Error = Error;
 
     fn serialize_field<T: ?Sized>(&mut self, _key: &'static str, value: &T) -> Result<()>
+    where
+        T: ser::Serialize,
+    {
+        ser::SerializeTupleStruct::serialize_field(self, value)
+    }
+
+    fn end(self) -> Result<Self::Ok> {
+        ser::SerializeTupleStruct::end(self)
+    }
+}
+
+impl<'a, 'b, W: Writer> ser::SerializeTupleStruct for SerializeStruct<'a, 'b, W> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_field<T: ?Sized>(&mut self, value: &T) -> Result<()>
     where
         T: ser::Serialize,
     {
         if self.first {
             if self.ser.options.value_sep {
-                self.ser.buf.push(b'=')?;
+                self.ser.push_sep(b'=');
             }
         } else {
-            self.ser.buf.push(b',')?;
+            self.ser.push_sep(b',');
         }
         self.first = false;
 
@@ -37,9 +51,7 @@ impl<'a, 'b, const B: usize, const C: usize> ser::SerializeStruct
     }
 
     fn end(self) -> Result<Self::Ok> {
-        self.ser
-            .buf
-            .extend_from_slice(self.ser.options.termination.as_bytes())?;
-        Ok(())
+        self.ser.clear_sep();
+        self.ser.write(self.ser.options.termination.as_bytes())
     }
 }